@@ -0,0 +1,159 @@
+//! Benchmarks of fuzzy matching over synthetic path corpora.
+//!
+//! Run with `cargo bench --bench fuzzy`, and compare with a baseline by
+//! `--save-baseline <NAME>` and `--baseline <NAME>`.
+
+use criterion::{
+  criterion_group, criterion_main, BenchmarkId, Criterion, Throughput,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use j2::fuzzy::{char_mask, EditDist};
+
+const WORDS: &[&str] = &[
+  "src",
+  "lib",
+  "bin",
+  "test",
+  "docs",
+  "node_modules",
+  "target",
+  "build",
+  "j2",
+  "repos",
+  "github.com",
+  "lumiknit",
+  "FooBar",
+  "client",
+  "server",
+  "api",
+  "v2",
+  "utils",
+  "config",
+  "main.rs",
+  "index.ts",
+  "README.md",
+  "assets",
+  "img",
+  "2024",
+  "release",
+  "Makefile",
+  "scripts",
+  "core",
+];
+
+/// Paths such as `repos: /github.com/lumiknit/src/FooBar/main.rs`
+fn corpus(len: usize) -> Vec<String> {
+  let mut rng = StdRng::seed_from_u64(len as u64);
+  (0..len)
+    .map(|_| {
+      let base = WORDS[rng.random_range(0..WORDS.len())];
+      let depth = rng.random_range(1..8);
+      let mut path = format!("{}: ", base);
+      for _ in 0..depth {
+        path.push('/');
+        path.push_str(WORDS[rng.random_range(0..WORDS.len())]);
+        if rng.random_bool(0.2) {
+          path.push_str(&rng.random_range(0..100).to_string());
+        }
+      }
+      path
+    })
+    .collect()
+}
+
+fn matcher(query: &str) -> EditDist {
+  let mut ed = EditDist::new();
+  ed.update_query(&query.chars().collect::<Vec<_>>());
+  ed
+}
+
+fn bench_run(c: &mut Criterion) {
+  let mut group = c.benchmark_group("run");
+  for len in [10_000, 100_000, 1_000_000] {
+    let paths = corpus(len);
+    group.throughput(Throughput::Elements(len as u64));
+    if len >= 1_000_000 {
+      group.sample_size(10);
+    }
+    // Short, long, abbreviation and missing queries
+    for query in ["j2", "srcmainrs", "fbc", "zzq"] {
+      let mut ed = matcher(query);
+      group.bench_with_input(BenchmarkId::new(query, len), &paths, |b, p| {
+        b.iter(|| p.iter().filter_map(|path| ed.run(path)).count())
+      });
+    }
+  }
+  group.finish();
+}
+
+fn bench_prefilter(c: &mut Criterion) {
+  let mut group = c.benchmark_group("prefilter");
+  let paths = corpus(100_000);
+  let masks: Vec<u64> = paths.iter().map(|p| char_mask(p)).collect();
+  group.throughput(Throughput::Elements(paths.len() as u64));
+  for query in ["fbc", "zzq"] {
+    let mut ed = matcher(query);
+    group.bench_function(query, |b| {
+      b.iter(|| {
+        paths
+          .iter()
+          .zip(&masks)
+          .filter_map(|(path, mask)| {
+            ed.may_match(*mask).then(|| ed.run(path)).flatten()
+          })
+          .count()
+      })
+    });
+  }
+  group.finish();
+}
+
+fn bench_unicode(c: &mut Criterion) {
+  let mut group = c.benchmark_group("unicode");
+  let paths: Vec<String> = corpus(10_000)
+    .into_iter()
+    .enumerate()
+    .map(|(i, p)| match i % 3 {
+      0 => format!("{}/Café", p),
+      1 => format!("{}/한글", p),
+      _ => p,
+    })
+    .collect();
+  group.throughput(Throughput::Elements(paths.len() as u64));
+  let mut ed = matcher("cafe");
+  ed.ignore_diacritics(true);
+  group.bench_function("ignore_diacritics", |b| {
+    b.iter(|| paths.iter().filter_map(|path| ed.run(path)).count())
+  });
+  let mut ed = matcher("ㅎㄱ");
+  ed.jamo(true);
+  group.bench_function("jamo", |b| {
+    b.iter(|| paths.iter().filter_map(|path| ed.run(path)).count())
+  });
+  group.finish();
+}
+
+fn bench_tolerant(c: &mut Criterion) {
+  let mut group = c.benchmark_group("run_tolerant");
+  let paths = corpus(10_000);
+  group.throughput(Throughput::Elements(paths.len() as u64));
+  for typos in [1, 2] {
+    let mut ed = matcher("reops");
+    ed.typos(typos);
+    group.bench_with_input(BenchmarkId::new("typos", typos), &paths, |b, p| {
+      b.iter(|| p.iter().filter_map(|path| ed.run_tolerant(path)).count())
+    });
+  }
+  group.finish();
+}
+
+criterion_group!(
+  benches,
+  bench_run,
+  bench_prefilter,
+  bench_unicode,
+  bench_tolerant
+);
+criterion_main!(benches);
@@ -1,11 +1,118 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use j2::matcher::Mode;
+use j2::ui_finder::Height;
+
+use crate::format::OutputFormat;
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("ranked").args(["filter", "explain"])))]
+pub struct SelectArgs {
+  /// Without GUI, just pick the first match
+  #[clap(short = '1', long)]
+  pub first: bool,
+
+  /// Without GUI, print all matches ranked by cost
+  #[clap(long)]
+  pub filter: bool,
+
+  /// Maximum number of matches printed by --filter or --explain
+  #[clap(long, requires = "ranked")]
+  pub limit: Option<usize>,
+
+  /// Print the cost before each match of --filter, separated by a tab
+  #[clap(long, requires = "filter")]
+  pub with_scores: bool,
+
+  /// Without GUI, print the top matches (10 by default) with matched
+  /// characters and costs by weights, to tune J2_WEIGHTS
+  #[clap(long, conflicts_with_all = ["first", "filter", "mode"])]
+  pub explain: bool,
+
+  /// Matching mode of the query: fuzzy, exact, prefix, regex or glob.
+  /// Also switched by Ctrl-s in the finder.
+  #[clap(long, value_name = "MODE", default_value_t = Mode::Fuzzy)]
+  pub mode: Mode,
+
+  /// Skip GUI and pick the match if there is exactly one match
+  #[clap(long)]
+  pub select_1: bool,
+
+  /// Skip GUI and exit with failure if there is no match
+  #[clap(long)]
+  pub exit_0: bool,
+
+  /// Match uppercase characters in the query case-sensitively
+  #[clap(long)]
+  pub smart_case: bool,
+
+  /// Normalize the query and paths with NFKC rather than NFC,
+  /// so compatibility characters such as 'ﬁ' match 'fi'
+  #[clap(long)]
+  pub nfkc: bool,
+
+  /// Ignore diacritics, so 'cafe' matches 'Café'
+  #[clap(long)]
+  pub ignore_diacritics: bool,
+
+  /// Match Hangul by jamo, so partially typed syllables match
+  /// and initial consonants such as 'ㅎㄱ' match '한글'
+  #[clap(long)]
+  pub jamo: bool,
+
+  /// Allow up to N mistyped or transposed query characters,
+  /// only if nothing matches the query exactly
+  #[clap(long, value_name = "N", default_value_t = 0)]
+  pub typos: usize,
+
+  /// Print the accepting action and the match, separated by a tab.
+  /// Also bind keys to accept with actions (edit, copy, pushd and run).
+  #[clap(long, conflicts_with = "filter")]
+  pub print_action: bool,
+}
+
+#[derive(Args)]
+pub struct FinderArgs {
+  /// Render the finder below the cursor with the height,
+  /// in lines (20) or a percentage of the screen (40%)
+  #[clap(long)]
+  pub height: Option<Height>,
+
+  /// Render the finder below the cursor, with 40% of the screen
+  #[clap(long, conflicts_with = "height")]
+  pub inline: bool,
+
+  /// Put the query at the top, and list matches from the top
+  #[clap(long)]
+  pub reverse: bool,
+
+  /// Do not capture the mouse in the finder
+  #[clap(long)]
+  pub no_mouse: bool,
+
+  /// Colors of the finder, applied after J2_COLOR
+  /// (e.g. 'light,prompt:blue:bold,match:#ff8800')
+  #[clap(long)]
+  pub color: Option<String>,
+}
+
+impl FinderArgs {
+  /// Height of the inline finder, or None for the full screen
+  pub fn height(&self) -> Option<Height> {
+    match self.height {
+      Some(height) => Some(height),
+      None if self.inline => Some(Height::Percent(40)),
+      None => None,
+    }
+  }
+}
 
 #[derive(Subcommand)]
 pub enum Command {
   #[clap(about = "Initialization script for shell")]
   ShellInit {
     /// Type of shell
-    #[clap(help = "Type of shell (sh, pwsh)")]
+    #[clap(help = "Type of shell (sh, fish, pwsh)")]
     shell: Option<String>,
   },
 
@@ -19,9 +126,16 @@ pub enum Command {
     #[clap(short, long)]
     base: Vec<String>,
 
-    /// Without GUI, just pick the first match
-    #[clap(short = '1', long)]
-    first: bool,
+    /// Base directories to search, taken as-is,
+    /// while --base is split by ':'
+    #[clap(long, value_name = "PATH")]
+    dir: Vec<String>,
+
+    #[command(flatten)]
+    select: SelectArgs,
+
+    #[command(flatten)]
+    finder: FinderArgs,
 
     /// Allow (non-directory) files to be included
     #[clap(short, long)]
@@ -32,6 +146,18 @@ pub enum Command {
     all: bool,
   },
 
+  #[clap(about = "Execute fuzzy find over lines from stdin")]
+  Pick {
+    /// Query string
+    query: Vec<String>,
+
+    #[command(flatten)]
+    select: SelectArgs,
+
+    #[command(flatten)]
+    finder: FinderArgs,
+  },
+
   #[clap(about = "Clone a repository")]
   Clone {
     /// URL of git remote repository
@@ -68,6 +194,10 @@ pub enum Command {
 #[command(author, version, about)]
 #[command(propagate_version = true)]
 pub struct Cli {
+  /// Output format of printed records
+  #[clap(long, global = true, value_enum, default_value_t)]
+  pub format: OutputFormat,
+
   #[clap(subcommand)]
   pub command: Command,
 }
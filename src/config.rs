@@ -1,4 +1,6 @@
-use std::env;
+//! Configuration loaded from environment variables.
+
+use std::{env, fmt};
 
 pub struct Config {
   // Clone config
@@ -10,21 +12,74 @@ pub struct Config {
   pub jones_path: String,
 }
 
-fn get_var(name: &str) -> String {
-  env::var(name).expect(&format!("Please set env ${}", name))
+/// Config of the finder UI, which is all optional
+#[derive(Default)]
+pub struct FinderConfig {
+  pub keymap: Option<String>,
+  // Directory of query histories, default to ~/.J2-history
+  pub history_dir: Option<String>,
+  // Theme spec of J2_COLOR
+  pub color: Option<String>,
+  // Whether NO_COLOR is set (https://no-color.org)
+  pub no_color: bool,
+  // Weights spec of J2_WEIGHTS, also used without the UI
+  pub weights: Option<String>,
 }
 
-fn get_list_var(name: &str) -> Vec<String> {
-  get_var(name).split(":").map(|s| s.to_string()).collect()
+impl FinderConfig {
+  pub fn from_env() -> Self {
+    let history_dir = env::var("J2_HISTORY_DIR").ok().or_else(|| {
+      env::var("HOME")
+        .ok()
+        .map(|home| format!("{}/.J2-history", home))
+    });
+    Self {
+      keymap: env::var("J2_KEYMAP").ok(),
+      history_dir,
+      color: env::var("J2_COLOR").ok(),
+      no_color: env::var("NO_COLOR").is_ok_and(|v| !v.is_empty()),
+      weights: env::var("J2_WEIGHTS").ok(),
+    }
+  }
+}
+
+/// Error for a required environment variable which is not set
+#[derive(Debug)]
+pub struct ConfigError {
+  pub var: String,
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Please set env ${}", self.var)
+  }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn get_var(name: &str) -> Result<String, ConfigError> {
+  env::var(name).map_err(|_| ConfigError {
+    var: name.to_string(),
+  })
+}
+
+fn get_list_var(name: &str) -> Result<Vec<String>, ConfigError> {
+  Ok(get_var(name)?.split(":").map(|s| s.to_string()).collect())
 }
 
 impl Config {
-  pub fn from_env() -> Self {
-    Self {
-      repos_path: get_var("J2_REPOS_PATH"),
-      find_base_paths: get_list_var("J2_FIND_BASE_PATHS"),
+  /// Load config from environment variables
+  pub fn try_from_env() -> Result<Self, ConfigError> {
+    Ok(Self {
+      repos_path: get_var("J2_REPOS_PATH")?,
+      find_base_paths: get_list_var("J2_FIND_BASE_PATHS")?,
       ignore_file_path: env::var("J2_IGNORE").ok(),
-      jones_path: get_var("J2_JONES_PATH"),
-    }
+      jones_path: get_var("J2_JONES_PATH")?,
+    })
+  }
+
+  /// Load config from environment variables, and panic if some are missing
+  pub fn from_env() -> Self {
+    Self::try_from_env().unwrap_or_else(|e| panic!("{}", e))
   }
 }
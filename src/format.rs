@@ -0,0 +1,162 @@
+// Output formats for records printed by subcommands
+
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+  /// Only the main field of each record (e.g. path)
+  #[default]
+  Plain,
+  /// All fields of each record separated by tabs
+  Tsv,
+  /// A JSON object per line
+  Json,
+}
+
+pub enum Value {
+  Str(String),
+  Num(u64),
+  Null,
+}
+
+/// A record to print. The first field is the main field for plain output.
+#[derive(Default)]
+pub struct Record {
+  fields: Vec<(&'static str, Value)>,
+}
+
+impl Record {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn str(mut self, key: &'static str, value: &str) -> Self {
+    self.fields.push((key, Value::Str(value.to_string())));
+    self
+  }
+
+  pub fn num(mut self, key: &'static str, value: u64) -> Self {
+    self.fields.push((key, Value::Num(value)));
+    self
+  }
+
+  pub fn opt_str(self, key: &'static str, value: Option<&str>) -> Self {
+    match value {
+      Some(v) => self.str(key, v),
+      None => self.null(key),
+    }
+  }
+
+  pub fn opt_num(self, key: &'static str, value: Option<u64>) -> Self {
+    match value {
+      Some(v) => self.num(key, v),
+      None => self.null(key),
+    }
+  }
+
+  pub fn null(mut self, key: &'static str) -> Self {
+    self.fields.push((key, Value::Null));
+    self
+  }
+
+  fn to_plain(&self) -> String {
+    match self.fields.first() {
+      Some((_, Value::Str(s))) => s.clone(),
+      Some((_, Value::Num(n))) => n.to_string(),
+      _ => String::new(),
+    }
+  }
+
+  fn to_tsv(&self) -> String {
+    let values: Vec<String> = self
+      .fields
+      .iter()
+      .map(|(_, v)| match v {
+        // Tabs and newlines cannot be represented in TSV
+        Value::Str(s) => s.replace(['\t', '\n'], " "),
+        Value::Num(n) => n.to_string(),
+        Value::Null => String::new(),
+      })
+      .collect();
+    values.join("\t")
+  }
+
+  fn to_json(&self) -> String {
+    let fields: Vec<String> = self
+      .fields
+      .iter()
+      .map(|(k, v)| {
+        let v = match v {
+          Value::Str(s) => json_string(s),
+          Value::Num(n) => n.to_string(),
+          Value::Null => "null".to_string(),
+        };
+        format!("{}:{}", json_string(k), v)
+      })
+      .collect();
+    format!("{{{}}}", fields.join(","))
+  }
+
+  pub fn format(&self, format: OutputFormat) -> String {
+    match format {
+      OutputFormat::Plain => self.to_plain(),
+      OutputFormat::Tsv => self.to_tsv(),
+      OutputFormat::Json => self.to_json(),
+    }
+  }
+}
+
+fn json_string(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn json_strings_are_escaped() {
+    assert_eq!(json_string(r#"a"b\c"#), r#""a\"b\\c""#);
+    assert_eq!(json_string("a\nb\tc\r"), r#""a\nb\tc\r""#);
+    assert_eq!(json_string("\x01\x1f"), r#""\u0001\u001f""#);
+    assert_eq!(json_string("café 한글"), "\"café 한글\"");
+  }
+
+  #[test]
+  fn formats_records() {
+    let record = Record::new()
+      .str("path", "/a\tb\nc")
+      .num("cost", 3)
+      .opt_str("base", None)
+      .opt_num("depth", Some(2));
+    assert_eq!(record.format(OutputFormat::Plain), "/a\tb\nc");
+    assert_eq!(record.format(OutputFormat::Tsv), "/a b c\t3\t\t2");
+    assert_eq!(
+      record.format(OutputFormat::Json),
+      r#"{"path":"/a\tb\nc","cost":3,"base":null,"depth":2}"#
+    );
+  }
+
+  #[test]
+  fn plain_output_is_the_first_field() {
+    let record = Record::new().num("cost", 3).str("path", "/a");
+    assert_eq!(record.format(OutputFormat::Plain), "3");
+    let record = Record::new().null("path").str("jone", "a");
+    assert_eq!(record.format(OutputFormat::Plain), "");
+    assert_eq!(Record::new().format(OutputFormat::Plain), "");
+  }
+}
@@ -1,83 +1,489 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::hangul;
+
+#[derive(Clone)]
 pub struct EditDist {
+  // Query as typed, normalized again when options change
+  q_input: Vec<char>,
+  // Folded query, and the normalized one
   q: Vec<char>,
+  q_orig: Vec<char>,
+  // Whether each query character should match case-sensitively
+  q_exact: Vec<bool>,
+  // Mask of query characters by `char_mask`,
+  // and whether the query is ASCII to match bytes of ASCII targets
+  q_mask: u64,
+  q_ascii: bool,
+  smart_case: bool,
+  form: Form,
+  weights: Weights,
+  // Maximum number of typos of `run_tolerant`
+  typos: usize,
   d: [Vec<u32>; 2],
+  dt: Vec<u32>,
+  // Folded target, the normalized one,
+  // and the index of the target character each one comes from
   target_chars: Vec<char>,
+  target_orig: Vec<char>,
+  target_src: Vec<usize>,
+  target_buf: Vec<char>,
+}
+
+/// Normalization form of queries and targets
+#[derive(Clone, Copy, Default)]
+struct Form {
+  // NFKC rather than NFC
+  compat: bool,
+  // Drop combining marks, such as accents
+  strip_marks: bool,
+  // Decompose Hangul into jamo
+  jamo: bool,
+}
+
+impl Form {
+  /// Normalize the text, and push each character with the index of the
+  /// text character it comes from.
+  /// Characters are normalized in clusters of a base and following marks,
+  /// so indices are kept even if the number of characters changes.
+  fn normalize(self, text: &[char], out: &mut Vec<char>, src: &mut Vec<usize>) {
+    let mut i = 0;
+    while i < text.len() {
+      let mut j = i + 1;
+      while j < text.len() && Self::continues_cluster(text[j]) {
+        j += 1;
+      }
+      let cluster = text[i..j].iter().copied();
+      let jamo = self.jamo;
+      let mut push = |c: char| {
+        let mut push_src = |c: char| {
+          out.push(c);
+          src.push(i);
+        };
+        if !(jamo && hangul::decompose(c, &mut push_src)) {
+          push_src(c);
+        }
+      };
+      if j == i + 1 && text[i].is_ascii() {
+        push(text[i]);
+      } else if self.strip_marks {
+        let base: Vec<char> = if self.compat {
+          cluster.nfkd().filter(|c| !is_combining_mark(*c)).collect()
+        } else {
+          cluster.nfd().filter(|c| !is_combining_mark(*c)).collect()
+        };
+        base.into_iter().nfc().for_each(push);
+      } else if self.compat {
+        cluster.nfkc().for_each(push);
+      } else {
+        cluster.nfc().for_each(push);
+      }
+      i = j;
+    }
+  }
+
+  /// Check if the character is composed with the previous one,
+  /// such as combining marks and Hangul vowels and final consonants
+  fn continues_cluster(c: char) -> bool {
+    !c.is_ascii()
+      && (is_combining_mark(c) || ('\u{1160}'..='\u{11FF}').contains(&c))
+  }
+}
+
+/// Simple case folding of a character
+pub(crate) fn fold(c: char) -> char {
+  if c.is_ascii() {
+    return c.to_ascii_lowercase();
+  }
+  // Lowercase characters which are folded into other ones
+  match c {
+    'ς' => 'σ',
+    'ſ' => 's',
+    'ϐ' => 'β',
+    'ϑ' => 'θ',
+    'ϕ' => 'φ',
+    'ϖ' => 'π',
+    'ϰ' => 'κ',
+    'ϱ' => 'ρ',
+    'ϵ' => 'ε',
+    'ẛ' => 'ṡ',
+    '\u{1FBE}' => 'ι',
+    _ => {
+      // Full lowercase mappings, such as 'İ', are not simple folding
+      let mut lower = c.to_lowercase();
+      match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+      }
+    }
+  }
+}
+
+/// Weights of costs, which can be tuned for the trees to search
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weights {
+  /// Each target character skipped
+  pub insert: u32,
+  /// Each match not right after the previous one
+  pub non_contd: u32,
+  /// Each match in the middle of a word
+  pub non_abbrev: u32,
+  /// Each match outside of the basename
+  pub non_basename: u32,
+  /// Each match at a word start, but not at a path component start
+  pub non_segment_start: u32,
+  /// Each path separator skipped between matches
+  pub skip_segment: u32,
+  /// Each path separator in the target
+  pub depth: u32,
+}
+
+impl Default for Weights {
+  fn default() -> Self {
+    Self {
+      insert: 2,
+      non_contd: 1,
+      non_abbrev: 7,
+      non_basename: 3,
+      non_segment_start: 1,
+      skip_segment: 1,
+      depth: 1,
+    }
+  }
+}
+
+impl Weights {
+  /// Names of weights in specs and explanations
+  pub const NAMES: [&'static str; 7] = [
+    "insert",
+    "non-contd",
+    "non-abbrev",
+    "non-basename",
+    "non-segment-start",
+    "skip-segment",
+    "depth",
+  ];
+
+  fn get_mut(&mut self, name: &str) -> Option<&mut u32> {
+    Some(match name {
+      "insert" => &mut self.insert,
+      "non-contd" => &mut self.non_contd,
+      "non-abbrev" => &mut self.non_abbrev,
+      "non-basename" => &mut self.non_basename,
+      "non-segment-start" => &mut self.non_segment_start,
+      "skip-segment" => &mut self.skip_segment,
+      "depth" => &mut self.depth,
+      _ => return None,
+    })
+  }
+
+  /// Apply a spec such as `insert=1,non-abbrev=5`
+  pub fn apply_spec(&mut self, spec: &str) -> Result<(), String> {
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+      let (name, value) = item
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid weight spec '{}'", item))?;
+      let weight = self
+        .get_mut(name.trim())
+        .ok_or_else(|| format!("Invalid weight '{}'", name))?;
+      *weight = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value '{}'", value))?;
+    }
+    Ok(())
+  }
+}
+
+/// Cost of a match, split into weights
+pub struct Explanation {
+  pub cost: u32,
+  /// Costs of each weight by name, or the length for the empty query
+  pub costs: Vec<(&'static str, u32)>,
+  /// Target characters matched to the query
+  pub matched: Vec<bool>,
+}
+
+/// Mask of characters in the text, to reject targets before matching
+/// by `EditDist::may_match`.
+/// Non-ASCII texts have all bits, because they may match ASCII queries
+/// after normalization.
+pub fn char_mask(text: &str) -> u64 {
+  if !text.is_ascii() {
+    return u64::MAX;
+  }
+  text
+    .bytes()
+    .fold(0, |mask, b| mask | char_bit(char::from(b)))
+}
+
+/// Bit of a folded character in masks
+fn char_bit(c: char) -> u64 {
+  match c.to_ascii_lowercase() {
+    c @ 'a'..='z' => 1 << (c as u32 - 'a' as u32),
+    c @ '0'..='9' => 1 << (26 + c as u32 - '0' as u32),
+    c if c.is_ascii() => 1 << (36 + c as u32 % 27),
+    _ => 1 << 63,
+  }
+}
+
+/// Key to order matches of the same cost: shorter ones first,
+/// then in lexical order
+pub fn tie_key(text: &str) -> (usize, &str) {
+  (text.chars().count(), text)
+}
+
+impl Default for EditDist {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl EditDist {
   pub fn new() -> Self {
     Self {
+      q_input: vec![],
       q: vec![],
+      q_orig: vec![],
+      q_exact: vec![],
+      q_mask: 0,
+      q_ascii: true,
+      smart_case: false,
+      form: Form::default(),
+      weights: Weights::default(),
+      typos: 0,
       d: [vec![], vec![]],
+      dt: vec![],
       target_chars: vec![],
+      target_orig: vec![],
+      target_src: vec![],
+      target_buf: vec![],
     }
   }
 
-  pub fn update_query<'a>(&'a mut self, q_vec: &Vec<char>) -> &'a mut Self {
+  /// Smart-case mode, where uppercase query characters match only
+  /// the same uppercase characters
+  pub fn smart_case(&mut self, smart_case: bool) -> &mut Self {
+    self.smart_case = smart_case;
+    self.reset_query()
+  }
+
+  /// Normalize with NFKC rather than NFC,
+  /// so compatibility characters such as 'ﬁ' and '①' match 'fi' and '1'
+  pub fn nfkc(&mut self, nfkc: bool) -> &mut Self {
+    self.form.compat = nfkc;
+    self.reset_query()
+  }
+
+  /// Ignore diacritics, so `cafe` matches `Café`
+  pub fn ignore_diacritics(&mut self, ignore: bool) -> &mut Self {
+    self.form.strip_marks = ignore;
+    self.reset_query()
+  }
+
+  /// Match Hangul by jamo, so partially typed syllables match
+  /// and initial consonants such as `ㅎㄱ` match `한글`
+  pub fn jamo(&mut self, jamo: bool) -> &mut Self {
+    self.form.jamo = jamo;
+    self.reset_query()
+  }
+
+  /// Weights of costs
+  pub fn weights(&mut self, weights: Weights) -> &mut Self {
+    self.weights = weights;
+    self
+  }
+
+  /// Maximum number of typos allowed by `run_tolerant`
+  pub fn typos(&mut self, typos: usize) -> &mut Self {
+    self.typos = typos;
+    self
+  }
+
+  pub fn max_typos(&self) -> usize {
+    self.typos
+  }
+
+  pub fn is_smart_case(&self) -> bool {
+    self.smart_case
+  }
+
+  /// Check if appending the character to the query only removes matches.
+  /// Combining marks and Hangul jamo may be composed with the previous
+  /// character, such as 'e' and U+0301 into 'é', which matches others.
+  pub fn narrows_on(&self, c: char) -> bool {
+    !Form::continues_cluster(c)
+  }
+
+  fn reset_query(&mut self) -> &mut Self {
+    let q = std::mem::take(&mut self.q_input);
+    self.update_query(&q)
+  }
+
+  pub fn update_query<'a>(&'a mut self, q_vec: &[char]) -> &'a mut Self {
+    self.q_input.clear();
+    self.q_input.extend_from_slice(q_vec);
+    self.q_orig.clear();
+    let mut src = vec![];
+    self.form.normalize(q_vec, &mut self.q_orig, &mut src);
     self.q.clear();
-    self.q.extend(q_vec.iter().map(|c| c.to_ascii_lowercase()));
+    self.q.extend(self.q_orig.iter().map(|c| fold(*c)));
+    self.q_exact.clear();
+    let smart_case = self.smart_case;
+    self
+      .q_exact
+      .extend(self.q_orig.iter().map(|c| smart_case && c.is_uppercase()));
+    self.q_mask = self.q.iter().fold(0, |mask, c| mask | char_bit(*c));
+    self.q_ascii = self.q_orig.iter().all(char::is_ascii);
     self.d[0].resize(self.q.len() + 1, 0);
     self.d[1].resize(self.q.len() + 1, 0);
     self
   }
 
+  /// Normalize and fold the target
+  fn prepare(&mut self, target: &str) {
+    self.target_orig.clear();
+    self.target_src.clear();
+    if target.is_ascii() {
+      self.target_orig.extend(target.bytes().map(char::from));
+      self.target_src.extend(0..target.len());
+    } else {
+      self.target_buf.clear();
+      self.target_buf.extend(target.chars());
+      self.form.normalize(
+        &self.target_buf,
+        &mut self.target_orig,
+        &mut self.target_src,
+      );
+    }
+    self.target_chars.clear();
+    self
+      .target_chars
+      .extend(self.target_orig.iter().map(|c| fold(*c)));
+  }
+
+  /// Check if a target of the mask by `char_mask` may match the query,
+  /// without typos
+  pub fn may_match(&self, mask: u64) -> bool {
+    mask & self.q_mask == self.q_mask
+  }
+
+  /// First index of the ASCII target where the query starts,
+  /// if the query is a subsequence of the target
+  fn ascii_first_hit(&self, target: &[u8]) -> Option<usize> {
+    let matches = |j: usize, b: u8| {
+      if self.q_exact[j] {
+        self.q_orig[j] as u32 == b as u32
+      } else {
+        self.q[j] as u32 == b.to_ascii_lowercase() as u32
+      }
+    };
+    let first_hit = target.iter().position(|&b| matches(0, b))?;
+    let mut matched = 0;
+    for &b in &target[first_hit..] {
+      if matches(matched, b) {
+        matched += 1;
+        if matched == self.q.len() {
+          return Some(first_hit);
+        }
+      }
+    }
+    None
+  }
+
+  /// Positions of target characters matched to the query, picked greedily.
+  /// Only for highlighting, so they may differ from the best match.
+  pub fn match_positions(&mut self, target: &str) -> Vec<bool> {
+    self.prepare(target);
+    let mut matched = vec![false; target.chars().count()];
+    let mut j = 0;
+    for idx in 0..self.target_chars.len() {
+      if j < self.q.len() && self.matches(j, idx) {
+        matched[self.target_src[idx]] = true;
+        j += 1;
+      }
+    }
+    matched
+  }
+
+  /// Check if the j-th query character matches the idx-th target character
+  fn matches(&self, j: usize, idx: usize) -> bool {
+    if self.q_exact[j] {
+      self.q_orig[j] == self.target_orig[idx]
+    } else {
+      self.q[j] == self.target_chars[idx]
+    }
+  }
+
+  /// Check if a word starts at the character after the previous one,
+  /// by separators, camelCase or letter/digit transitions
+  fn is_boundary(prev: char, c: char) -> bool {
+    Self::is_ascii_sep(prev)
+      || (prev.is_lowercase() && c.is_uppercase())
+      || (prev.is_alphabetic() && c.is_numeric())
+      || (prev.is_numeric() && c.is_alphabetic())
+  }
+
   fn is_ascii_sep(c: char) -> bool {
-    ('\x00' <= c && c < '0')
+    ('\x00'..'0').contains(&c)
       || ('9' < c && c < 'A')
       || ('Z' < c && c < 'a')
       || ('z' < c && c < '\x7f')
   }
 
-  const COST_INSERT: u32 = 2;
-  const COST_MATCH_NON_CONTD: u32 = 1;
-  const COST_MATCH_NON_ABBREV: u32 = 7;
+  fn is_path_sep(c: char) -> bool {
+    c == '/' || c == '\\'
+  }
+
+  // Each typo, which costs more than matches without typos
+  const COST_TYPO: u32 = 1 << 16;
 
-  pub fn run(&mut self, target: &String) -> Option<u32> {
+  pub fn run(&mut self, target: &str) -> Option<u32> {
     // If the query is empty, just return inverse of length
     if self.q.is_empty() {
       // Then length is a cost
       return Some(target.len() as u32);
     }
 
-    // Convert target string into chars
-    self.target_chars.clear();
-    self
-      .target_chars
-      .extend(target.chars().map(|c| c.to_ascii_lowercase()));
+    // Check if the query is contained in the target in linear time,
+    // in bytes before converting ASCII targets
+    if self.q_ascii && target.is_ascii() {
+      let first_hit = self.ascii_first_hit(target.as_bytes())?;
+      self.prepare(target);
+      return Some(self.run_from(first_hit));
+    }
 
-    // Check if the query is contained in the target in linear time
+    // Convert target string into normalized chars
+    self.prepare(target);
 
     // Find first hit
-    let first_hit = {
-      let q_first = self.q[0];
-      let mut h = None;
-      for i in 0..self.target_chars.len() {
-        if self.target_chars[i] == q_first {
-          h = Some(i);
-          break;
-        }
-      }
-      h?
-    };
+    let first_hit =
+      (0..self.target_chars.len()).find(|&i| self.matches(0, i))?;
 
     // Then, find the rest of the query
     {
-      let mut q_iter = self.q.iter();
-      let mut q = *q_iter.next().unwrap();
-      for i in first_hit..self.target_chars.len() {
-        if self.target_chars[i] == q {
-          if let Some(n) = q_iter.next() {
-            q = *n;
-          } else {
+      let mut matched = 0;
+      for idx in first_hit..self.target_chars.len() {
+        if self.matches(matched, idx) {
+          matched += 1;
+          if matched == self.q.len() {
             break;
           }
         }
       }
-      if let Some(_) = q_iter.next() {
+      if matched < self.q.len() {
         return None;
       }
     }
 
+    Some(self.run_from(first_hit))
+  }
+
+  /// Cost of the prepared target, which contains the query from first_hit
+  fn run_from(&mut self, first_hit: usize) -> u32 {
+    let (basename_start, depth) = self.components();
+
     // If the query is contained in the target, calculate the edit distance
     self.d[(first_hit + 1) % 2].fill(u32::MAX);
 
@@ -91,8 +497,8 @@ impl EditDist {
       // Because of skip, add first_hit to idx
       let idx = idx + first_hit;
 
-      // Check if the previous character is a separator;
-      let after_sep = Self::is_ascii_sep(pp);
+      let position_cost = self.position_cost(idx, basename_start);
+      let skip_cost = self.skip_cost(pc);
 
       // Calculate index of d
       i = idx % 2;
@@ -102,26 +508,619 @@ impl EditDist {
       let mut qp = '\x01';
       for (j, &qc) in self.q.iter().enumerate() {
         let mut cost: u32 = u32::MAX;
-        if qc == pc {
+        if self.matches(j, idx) {
           if j == 0 {
-            cost = Self::COST_INSERT.saturating_mul(idx as u32);
+            cost = self.weights.insert.saturating_mul(idx as u32);
           } else {
             cost = self.d[zi][j - 1];
           }
-          if !after_sep {
-            cost = cost.saturating_add(Self::COST_MATCH_NON_ABBREV);
-          }
+          cost = cost.saturating_add(position_cost);
           if qp != pp {
-            cost = cost.saturating_add(Self::COST_MATCH_NON_CONTD);
+            cost = cost.saturating_add(self.weights.non_contd);
           }
         }
-        // Just insert from previous
-        cost = cost.min(self.d[zi][j].saturating_add(Self::COST_INSERT));
+        // Just insert from previous.
+        // Skipping a path separator between matches costs more,
+        // so matches in consecutive components are preferred.
+        let insert = if j + 1 < self.q.len() {
+          skip_cost
+        } else {
+          self.weights.insert
+        };
+        cost = cost.min(self.d[zi][j].saturating_add(insert));
         self.d[i][j] = cost;
         qp = qc;
       }
       pp = pc;
     }
-    Some(self.d[i][self.q.len() - 1])
+    let cost = self.d[i][self.q.len() - 1];
+    cost.saturating_add(self.weights.depth.saturating_mul(depth as u32))
+  }
+
+  /// Cost of the target allowing typos, up to the number set by `typos`.
+  /// A typo is a substituted query character, or two transposed ones.
+  /// Matches without typos cost the same as `run`, and each typo costs
+  /// more than any of them, so matches with typos are ranked below.
+  pub fn run_tolerant(&mut self, target: &str) -> Option<u32> {
+    if let Some(cost) = self.run(target) {
+      return Some(cost);
+    }
+    if self.typos == 0 {
+      return None;
+    }
+    // `run` may reject the target before preparing it
+    self.prepare(target);
+    let (m, n) = (self.q.len(), self.target_chars.len());
+    if n < m {
+      return None;
+    }
+    let (basename_start, depth) = self.components();
+
+    // Rows of the last three target characters, for transpositions,
+    // and a layer of each number of typos in a row
+    let layers = self.typos + 1;
+    let insert = self.weights.insert;
+    let at = |idx: usize, k: usize, j: usize| ((idx % 3) * layers + k) * m + j;
+    self.dt.clear();
+    self.dt.resize(3 * layers * m, u32::MAX);
+    // Cost of matching queries before j, ending before idx, with k typos
+    let before = |dt: &[u32], idx: usize, k: usize, j: usize| match (idx, j) {
+      (_, 0) if k == 0 => insert.saturating_mul(idx as u32),
+      (0, _) | (_, 0) => u32::MAX,
+      _ => dt[at(idx - 1, k, j - 1)],
+    };
+
+    for idx in 0..n {
+      let pc = self.target_chars[idx];
+      let pp = if idx > 0 {
+        self.target_chars[idx - 1]
+      } else {
+        '\x00'
+      };
+      let position_cost = self.position_cost(idx, basename_start);
+      let skip_cost = self.skip_cost(pc);
+      for k in 0..layers {
+        let mut qp = '\x01';
+        for (j, &qc) in self.q.iter().enumerate() {
+          let mut cost = if self.matches(j, idx) {
+            before(&self.dt, idx, k, j)
+          } else if k > 0 {
+            // Substitution
+            before(&self.dt, idx, k - 1, j)
+          } else {
+            u32::MAX
+          };
+          cost = cost.saturating_add(position_cost);
+          if qp != pp {
+            cost = cost.saturating_add(self.weights.non_contd);
+          }
+          if k > 0
+            && j > 0
+            && idx > 0
+            && self.matches(j, idx - 1)
+            && self.matches(j - 1, idx)
+          {
+            // Transposition of this and the previous query characters
+            let swapped = before(&self.dt, idx - 1, k - 1, j - 1);
+            cost = cost.min(swapped.saturating_add(position_cost));
+          }
+          let insert = if j + 1 < m {
+            skip_cost
+          } else {
+            self.weights.insert
+          };
+          if idx > 0 {
+            cost = cost.min(self.dt[at(idx - 1, k, j)].saturating_add(insert));
+          }
+          self.dt[at(idx, k, j)] = cost;
+          qp = qc;
+        }
+      }
+    }
+    let cost = (1..layers)
+      .map(|k| {
+        let typos = Self::COST_TYPO.saturating_mul(k as u32);
+        self.dt[at(n - 1, k, m - 1)].saturating_add(typos)
+      })
+      .min()
+      .filter(|cost| *cost != u32::MAX)?;
+    Some(cost.saturating_add(self.weights.depth.saturating_mul(depth as u32)))
+  }
+
+  /// Explain the cost of the target by weights, without typos.
+  /// Slower than `run`, because the whole table is kept to trace back.
+  pub fn explain(&mut self, target: &str) -> Option<Explanation> {
+    let cost = self.run(target)?;
+    let mut matched = vec![false; target.chars().count()];
+    if self.q.is_empty() {
+      let costs = vec![("length", cost)];
+      return Some(Explanation {
+        cost,
+        costs,
+        matched,
+      });
+    }
+    let (m, n) = (self.q.len(), self.target_chars.len());
+    let (basename_start, depth) = self.components();
+    // Costs are summed in u64, so the trace back does not break
+    // on costs saturated by large weights
+    let w = self.weights.clone();
+    let insert_cost = |ed: &Self, j: usize, idx: usize| {
+      let skip_segment = j + 1 < m && Self::is_path_sep(ed.target_chars[idx]);
+      w.insert as u64
+        + if skip_segment {
+          w.skip_segment as u64
+        } else {
+          0
+        }
+    };
+    let contd_cost = |ed: &Self, j: usize, idx: usize| {
+      let qp = if j > 0 { ed.q[j - 1] } else { '\x01' };
+      let pp = if idx > 0 {
+        ed.target_chars[idx - 1]
+      } else {
+        '\x00'
+      };
+      if qp != pp {
+        w.non_contd as u64
+      } else {
+        0
+      }
+    };
+    let position_cost = |ed: &Self, idx: usize| {
+      let (non_abbrev, non_segment_start, non_basename) =
+        ed.position_costs(idx, basename_start);
+      non_abbrev as u64 + non_segment_start as u64 + non_basename as u64
+    };
+
+    // The same table as `run`, but with all rows
+    let mut d = vec![u64::MAX; n * m];
+    for idx in 0..n {
+      for j in 0..m {
+        let mut cost = u64::MAX;
+        if self.matches(j, idx) {
+          cost = match (idx, j) {
+            (_, 0) => w.insert as u64 * idx as u64,
+            (0, _) => u64::MAX,
+            _ => d[(idx - 1) * m + j - 1],
+          };
+          cost = cost
+            .saturating_add(position_cost(self, idx))
+            .saturating_add(contd_cost(self, j, idx));
+        }
+        if idx > 0 {
+          let insert = insert_cost(self, j, idx);
+          cost = cost.min(d[(idx - 1) * m + j].saturating_add(insert));
+        }
+        d[idx * m + j] = cost;
+      }
+    }
+
+    // Trace back from the end, and sum costs in the order of names
+    let mut costs = [0u64; Weights::NAMES.len()];
+    let (mut idx, mut j) = (n - 1, m - 1);
+    loop {
+      let insert = if idx > 0 {
+        d[(idx - 1) * m + j].saturating_add(insert_cost(self, j, idx))
+      } else {
+        u64::MAX
+      };
+      if insert == d[idx * m + j] {
+        costs[0] += w.insert as u64;
+        costs[5] += insert_cost(self, j, idx) - w.insert as u64;
+        idx -= 1;
+        continue;
+      }
+      matched[self.target_src[idx]] = true;
+      let (non_abbrev, non_segment_start, non_basename) =
+        self.position_costs(idx, basename_start);
+      costs[1] += contd_cost(self, j, idx);
+      costs[2] += non_abbrev as u64;
+      costs[3] += non_basename as u64;
+      costs[4] += non_segment_start as u64;
+      if j == 0 {
+        costs[0] += w.insert as u64 * idx as u64;
+        break;
+      }
+      idx -= 1;
+      j -= 1;
+    }
+    costs[6] = w.depth as u64 * depth as u64;
+    let costs = costs.map(|c| u32::try_from(c).unwrap_or(u32::MAX));
+    let costs = Weights::NAMES.into_iter().zip(costs).collect();
+    Some(Explanation {
+      cost,
+      costs,
+      matched,
+    })
+  }
+
+  /// Start of the last path component (basename) and the depth,
+  /// ignoring trailing separators
+  fn components(&self) -> (usize, usize) {
+    let trimmed_len = self
+      .target_chars
+      .iter()
+      .rposition(|c| !Self::is_path_sep(*c))
+      .map_or(0, |i| i + 1);
+    let trimmed = &self.target_chars[..trimmed_len];
+    let basename_start = trimmed
+      .iter()
+      .rposition(|c| Self::is_path_sep(*c))
+      .map_or(0, |i| i + 1);
+    let depth = trimmed.iter().filter(|c| Self::is_path_sep(**c)).count();
+    (basename_start, depth)
+  }
+
+  /// Cost of matching the idx-th target character by its position,
+  /// such as in the middle of a word or outside of the basename
+  fn position_cost(&self, idx: usize, basename_start: usize) -> u32 {
+    let (non_abbrev, non_segment_start, non_basename) =
+      self.position_costs(idx, basename_start);
+    non_abbrev
+      .saturating_add(non_segment_start)
+      .saturating_add(non_basename)
+  }
+
+  /// Costs of `position_cost` by weights
+  fn position_costs(
+    &self,
+    idx: usize,
+    basename_start: usize,
+  ) -> (u32, u32, u32) {
+    let pc_orig = self.target_orig[idx];
+    let (pp, pp_orig) = if idx > 0 {
+      (self.target_chars[idx - 1], self.target_orig[idx - 1])
+    } else {
+      ('\x00', '\x00')
+    };
+    // Check if a word starts at the character
+    let after_sep = if self.form.jamo && hangul::is_jamo(pc_orig) {
+      // Each syllable starts a word, for initial consonant queries
+      idx == 0 || self.target_src[idx - 1] != self.target_src[idx]
+    } else {
+      Self::is_boundary(pp_orig, pc_orig)
+    };
+    let segment_start = idx == 0 || Self::is_path_sep(pp);
+    let w = &self.weights;
+    (
+      if after_sep { 0 } else { w.non_abbrev },
+      if after_sep && !segment_start {
+        w.non_segment_start
+      } else {
+        0
+      },
+      if idx < basename_start {
+        w.non_basename
+      } else {
+        0
+      },
+    )
+  }
+
+  /// Cost of skipping the target character between matches
+  fn skip_cost(&self, pc: char) -> u32 {
+    if Self::is_path_sep(pc) {
+      self
+        .weights
+        .insert
+        .saturating_add(self.weights.skip_segment)
+    } else {
+      self.weights.insert
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use proptest::prelude::*;
+
+  use super::*;
+
+  fn cost(query: &str, target: &str) -> u32 {
+    let mut ed = EditDist::new();
+    ed.update_query(&query.chars().collect::<Vec<_>>());
+    ed.run(target).unwrap()
+  }
+
+  fn is_match(query: &str, target: &str) -> bool {
+    let mut ed = EditDist::new();
+    ed.update_query(&query.chars().collect::<Vec<_>>());
+    ed.run(target).is_some()
+  }
+
+  #[test]
+  fn prefers_basename_matches() {
+    assert!(cost("j2", "x/j2") < cost("j2", "j2/x"));
+    assert!(
+      cost("j2", "repos: /github.com/lumiknit/J2")
+        < cost("j2", "repos: /github.com/lumiknit/node_modules/j2-util/lib")
+    );
+  }
+
+  #[test]
+  fn prefers_segment_starts() {
+    assert!(cost("b", "a-c/bc") < cost("b", "a/c-bc"));
+    assert!(cost("b", "a-bc") < cost("b", "abc"));
+  }
+
+  #[test]
+  fn prefers_consecutive_components() {
+    assert!(cost("ab", "a/b/x/z") < cost("ab", "a/x/b/z"));
+  }
+
+  #[test]
+  fn camel_case_and_digits_are_boundaries() {
+    assert!(cost("fbc", "FooBarClient") < cost("fbc", "Foobarclient"));
+    assert!(cost("f2", "file2") < cost("f2", "filex2"));
+    assert!(cost("v2b", "v2beta") < cost("v2b", "v2xbeta"));
+  }
+
+  #[test]
+  fn smart_case_matches_uppercase_exactly() {
+    let mut ed = EditDist::new();
+    ed.smart_case(true).update_query(&['F', 'b']);
+    assert!(ed.run("FooBar").is_some());
+    assert!(ed.run("foobar").is_none());
+    assert!(ed.run("fooBar").is_none());
+    ed.update_query(&['f', 'b']);
+    assert!(ed.run("FooBar").is_some());
+
+    // Case-insensitive without smart-case
+    ed.smart_case(false).update_query(&['F', 'b']);
+    assert!(ed.run("foobar").is_some());
+  }
+
+  #[test]
+  fn penalizes_depth() {
+    assert!(cost("c", "ab/c") < cost("c", "a/b/c"));
+    // Trailing separators are not components
+    assert_eq!(cost("c", "ab/c/"), cost("c", "ab/c") + 2);
+  }
+
+  #[test]
+  fn folds_unicode_case() {
+    assert!(is_match("äö", "ÄÖ"));
+    assert!(is_match("привет", "ПРИВЕТ"));
+    assert!(is_match("σοφος", "ΣΟΦΟΣ"));
+    assert!(is_match("σοφοσ", "σοφος"));
+  }
+
+  #[test]
+  fn normalizes_composed_and_decomposed() {
+    // NFD target and NFC query, and the reverse
+    assert!(is_match("caf\u{e9}", "Cafe\u{301}"));
+    assert!(is_match("cafe\u{301}", "Caf\u{e9}"));
+
+    let mut ed = EditDist::new();
+    ed.update_query(&['c', 'a', 'f', 'e']);
+    assert!(ed.run("Café").is_none());
+    ed.ignore_diacritics(true);
+    assert!(ed.run("Café").is_some());
+    assert!(ed.run("Cafe\u{301}").is_some());
+
+    ed.update_query(&['f', 'i']);
+    assert!(ed.run("\u{fb01}le").is_none());
+    ed.nfkc(true);
+    assert!(ed.run("\u{fb01}le").is_some());
+  }
+
+  #[test]
+  fn match_positions_map_to_target_chars() {
+    let mut ed = EditDist::new();
+    ed.ignore_diacritics(true).update_query(&['e', 'x']);
+    // 'e' and the combining accent are one character after normalization
+    assert_eq!(ed.match_positions("e\u{301}x"), vec![true, false, true]);
+    assert_eq!(ed.match_positions("éx"), vec![true, true]);
+  }
+
+  #[test]
+  fn matches_hangul_by_jamo() {
+    let query = |q: &str| q.chars().collect::<Vec<_>>();
+    let mut ed = EditDist::new();
+    ed.update_query(&query("ㅎㄱ"));
+    assert!(ed.run("한글").is_none());
+
+    ed.jamo(true);
+    assert!(ed.run("한글").is_some());
+    // Initial consonants are preferred to final ones
+    let cost = ed.run("한글").unwrap();
+    assert!(cost < ed.run("학생").unwrap());
+    assert_eq!(ed.match_positions("a한글"), vec![false, true, true]);
+
+    // Partially typed syllables
+    for q in ["하", "한그", "고", "닭"] {
+      ed.update_query(&query(q));
+      assert!(ed.run("한글/닭과").is_some(), "{}", q);
+    }
+    ed.update_query(&query("한"));
+    assert_eq!(ed.match_positions("하나"), vec![true, true]);
+  }
+
+  #[test]
+  fn tolerates_typos() {
+    let mut ed = EditDist::new();
+    ed.update_query(&"reops".chars().collect::<Vec<_>>());
+    assert!(ed.run("repos").is_none());
+    assert!(ed.run_tolerant("repos").is_none());
+
+    ed.typos(1);
+    // Transposition
+    let transposed = ed.run_tolerant("repos").unwrap();
+    assert!(transposed >= EditDist::COST_TYPO);
+    // Substitution
+    assert!(ed.run_tolerant("reaps").is_some());
+    assert!(ed.run_tolerant("rxxps").is_none());
+    // Exact matches cost the same as `run`
+    assert_eq!(ed.run_tolerant("my-reops"), ed.run("my-reops"));
+
+    ed.typos(2);
+    assert!(ed.run_tolerant("rxxps").is_some());
+  }
+
+  #[test]
+  fn explains_costs_by_weights() {
+    let mut ed = EditDist::new();
+    for (query, target) in [
+      ("j2", "repos: /github.com/lumiknit/J2"),
+      ("fbc", "src/foo/BarClient.rs"),
+      ("ab", "a/x/b/z/"),
+      ("", "abc"),
+    ] {
+      ed.update_query(&query.chars().collect::<Vec<_>>());
+      let explanation = ed.explain(target).unwrap();
+      assert_eq!(Some(explanation.cost), ed.run(target));
+      let sum: u32 = explanation.costs.iter().map(|(_, c)| c).sum();
+      assert_eq!(sum, explanation.cost, "{} for {}", target, query);
+      let matched = explanation.matched.iter().filter(|m| **m).count();
+      assert_eq!(matched, query.len());
+    }
+  }
+
+  #[test]
+  fn weights_are_configurable() {
+    let mut weights = Weights::default();
+    weights.apply_spec("depth=10, non-abbrev=0").unwrap();
+    assert_eq!(weights.depth, 10);
+    assert_eq!(weights.non_abbrev, 0);
+    assert!(weights.apply_spec("depth").is_err());
+    assert!(weights.apply_spec("width=1").is_err());
+    assert!(weights.apply_spec("depth=-1").is_err());
+
+    let mut ed = EditDist::new();
+    ed.update_query(&['c']);
+    let shallow = ed.run("a/b/c").unwrap();
+    ed.weights(weights);
+    assert_eq!(ed.run("a/b/c").unwrap(), shallow + 18);
+
+    // Large weights saturate costs instead of overflowing
+    let mut weights = Weights::default();
+    for name in Weights::NAMES {
+      weights.apply_spec(&format!("{}=4294967295", name)).unwrap();
+    }
+    ed.weights(weights);
+    ed.typos(1);
+    ed.update_query(&['g', 'a']);
+    for target in ["x/y/github/aa", "x/y/gxa/", "g/a"] {
+      assert_eq!(ed.run(target), Some(u32::MAX));
+      assert_eq!(ed.run_tolerant(target), Some(u32::MAX));
+      let explanation = ed.explain(target).unwrap();
+      assert_eq!(explanation.matched.iter().filter(|m| **m).count(), 2);
+    }
+  }
+
+  /// Reference cost by trying all alignments of the query in the target
+  fn reference_cost(ed: &mut EditDist, target: &str) -> Option<u32> {
+    fn best(ed: &EditDist, j: usize, start: usize, bs: usize) -> Option<u32> {
+      let (m, n) = (ed.q.len(), ed.target_chars.len());
+      let w = &ed.weights;
+      if j == m {
+        // Skip the rest
+        return Some(w.insert * (n - start) as u32);
+      }
+      (start..n)
+        .filter(|&idx| ed.matches(j, idx))
+        .filter_map(|idx| {
+          let skipped = if j == 0 {
+            w.insert * idx as u32
+          } else {
+            (start..idx).map(|i| ed.skip_cost(ed.target_chars[i])).sum()
+          };
+          let qp = if j > 0 { ed.q[j - 1] } else { '\x01' };
+          let pp = if idx > 0 {
+            ed.target_chars[idx - 1]
+          } else {
+            '\x00'
+          };
+          let contd = if qp != pp { w.non_contd } else { 0 };
+          let rest = best(ed, j + 1, idx + 1, bs)?;
+          Some(skipped + ed.position_cost(idx, bs) + contd + rest)
+        })
+        .min()
+    }
+    if ed.q.is_empty() {
+      return Some(target.len() as u32);
+    }
+    ed.prepare(target);
+    let (basename_start, depth) = ed.components();
+    let cost = best(ed, 0, 0, basename_start)?;
+    Some(cost + ed.weights.depth * depth as u32)
+  }
+
+  fn is_subsequence(query: &str, target: &str) -> bool {
+    let mut t = target.chars().map(|c| c.to_ascii_lowercase());
+    query
+      .chars()
+      .all(|q| t.any(|c| c == q.to_ascii_lowercase()))
+  }
+
+  fn query_chars(query: &str) -> Vec<char> {
+    query.chars().collect()
+  }
+
+  proptest! {
+    #[test]
+    fn matches_subsequences(query in "[a-cA-C/]{0,4}", target in "[a-cA-C/_1]{0,12}") {
+      let mut ed = EditDist::new();
+      ed.update_query(&query_chars(&query));
+      prop_assert_eq!(ed.run(&target).is_some(), is_subsequence(&query, &target));
+    }
+
+    #[test]
+    fn equals_reference_dp(query in "[a-cA-C/]{1,4}", target in "[a-cA-C/_1]{0,12}") {
+      let mut ed = EditDist::new();
+      ed.update_query(&query_chars(&query));
+      let expected = reference_cost(&mut ed, &target);
+      prop_assert_eq!(ed.run(&target), expected);
+      // Allowing typos keeps costs of exact matches
+      ed.typos(1);
+      if expected.is_some() {
+        prop_assert_eq!(ed.run_tolerant(&target), expected);
+      }
+    }
+
+    #[test]
+    fn masks_keep_matches(
+      query in "[a-cA-CéÉ/ㄱ]{0,4}",
+      target in "([a-cA-CéÉ/_1가각]|e\u{301}){0,12}",
+      ignore_diacritics: bool,
+      jamo: bool,
+    ) {
+      let mut ed = EditDist::new();
+      ed.ignore_diacritics(ignore_diacritics)
+        .jamo(jamo)
+        .update_query(&query_chars(&query));
+      if ed.run(&target).is_some() {
+        prop_assert!(ed.may_match(char_mask(&target)));
+      }
+    }
+
+    #[test]
+    fn prefix_beats_middle(query in "[a-z]{1,5}", rest in "[a-z/]{0,8}") {
+      let mut ed = EditDist::new();
+      ed.update_query(&query_chars(&query));
+      let prefix = ed.run(&format!("{}{}", query, rest)).unwrap();
+      let middle = ed.run(&format!("x{}{}", query, rest)).unwrap();
+      prop_assert!(prefix < middle);
+    }
+
+    #[test]
+    fn contiguous_beats_scattered(query in "[a-y]{2,5}") {
+      let mut ed = EditDist::new();
+      ed.update_query(&query_chars(&query));
+      let scattered: String = query.chars().flat_map(|c| [c, 'z']).collect();
+      let contiguous = format!("{}{}", query, "z".repeat(query.len()));
+      prop_assert!(ed.run(&contiguous).unwrap() < ed.run(&scattered).unwrap());
+    }
+
+    #[test]
+    fn explanations_sum_to_costs(query in "[a-c]{1,3}", target in "[a-cA-C/_1]{0,12}") {
+      let mut ed = EditDist::new();
+      ed.update_query(&query_chars(&query));
+      if let Some(explanation) = ed.explain(&target) {
+        let sum: u32 = explanation.costs.iter().map(|(_, c)| c).sum();
+        prop_assert_eq!(sum, explanation.cost);
+      }
+    }
   }
 }
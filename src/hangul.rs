@@ -0,0 +1,101 @@
+//! Decomposition of Hangul into compatibility jamo for matching.
+//!
+//! Syllables are split into initial consonants, vowels and final
+//! consonants, and compound vowels and consonants are split further,
+//! so a partially typed syllable (e.g. `하` or `과` typed as `고`)
+//! matches the whole one.
+
+const SYLLABLE_FIRST: u32 = 0xAC00;
+const SYLLABLE_LAST: u32 = 0xD7A3;
+const VOWELS: u32 = 21;
+const FINALS: u32 = 28;
+
+const INITIAL: [&str; 19] = [
+  "ㄱ", "ㄲ", "ㄴ", "ㄷ", "ㄸ", "ㄹ", "ㅁ", "ㅂ", "ㅃ", "ㅅ", "ㅆ", "ㅇ", "ㅈ",
+  "ㅉ", "ㅊ", "ㅋ", "ㅌ", "ㅍ", "ㅎ",
+];
+
+const MEDIAL: [&str; 21] = [
+  "ㅏ", "ㅐ", "ㅑ", "ㅒ", "ㅓ", "ㅔ", "ㅕ", "ㅖ", "ㅗ", "ㅗㅏ", "ㅗㅐ", "ㅗㅣ",
+  "ㅛ", "ㅜ", "ㅜㅓ", "ㅜㅔ", "ㅜㅣ", "ㅠ", "ㅡ", "ㅡㅣ", "ㅣ",
+];
+
+const FINAL: [&str; 28] = [
+  "", "ㄱ", "ㄲ", "ㄱㅅ", "ㄴ", "ㄴㅈ", "ㄴㅎ", "ㄷ", "ㄹ", "ㄹㄱ", "ㄹㅁ",
+  "ㄹㅂ", "ㄹㅅ", "ㄹㅌ", "ㄹㅍ", "ㄹㅎ", "ㅁ", "ㅂ", "ㅂㅅ", "ㅅ", "ㅆ", "ㅇ",
+  "ㅈ", "ㅊ", "ㅋ", "ㅌ", "ㅍ", "ㅎ",
+];
+
+/// Split a compound compatibility jamo, such as `ㄳ` and `ㅘ`
+fn split_compound(c: char) -> Option<&'static str> {
+  Some(match c {
+    'ㄳ' => "ㄱㅅ",
+    'ㄵ' => "ㄴㅈ",
+    'ㄶ' => "ㄴㅎ",
+    'ㄺ' => "ㄹㄱ",
+    'ㄻ' => "ㄹㅁ",
+    'ㄼ' => "ㄹㅂ",
+    'ㄽ' => "ㄹㅅ",
+    'ㄾ' => "ㄹㅌ",
+    'ㄿ' => "ㄹㅍ",
+    'ㅀ' => "ㄹㅎ",
+    'ㅄ' => "ㅂㅅ",
+    'ㅘ' => "ㅗㅏ",
+    'ㅙ' => "ㅗㅐ",
+    'ㅚ' => "ㅗㅣ",
+    'ㅝ' => "ㅜㅓ",
+    'ㅞ' => "ㅜㅔ",
+    'ㅟ' => "ㅜㅣ",
+    'ㅢ' => "ㅡㅣ",
+    _ => return None,
+  })
+}
+
+/// Pass jamo of the character to the function,
+/// and return false if the character is not Hangul to decompose.
+/// The first jamo of a syllable is its initial consonant.
+pub fn decompose(c: char, f: impl FnMut(char)) -> bool {
+  let code = c as u32;
+  if (SYLLABLE_FIRST..=SYLLABLE_LAST).contains(&code) {
+    let index = code - SYLLABLE_FIRST;
+    let parts = [
+      INITIAL[(index / (VOWELS * FINALS)) as usize],
+      MEDIAL[(index / FINALS % VOWELS) as usize],
+      FINAL[(index % FINALS) as usize],
+    ];
+    parts.iter().flat_map(|s| s.chars()).for_each(f);
+    true
+  } else if let Some(jamo) = split_compound(c) {
+    jamo.chars().for_each(f);
+    true
+  } else {
+    false
+  }
+}
+
+/// Check if the character is a compatibility jamo
+pub fn is_jamo(c: char) -> bool {
+  ('\u{3131}'..='\u{318E}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn jamo(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+      if !decompose(c, |j| out.push(j)) {
+        out.push(c);
+      }
+    }
+    out
+  }
+
+  #[test]
+  fn decomposes_syllables_and_compounds() {
+    assert_eq!(jamo("한글"), "ㅎㅏㄴㄱㅡㄹ");
+    assert_eq!(jamo("닭과"), "ㄷㅏㄹㄱㄱㅗㅏ");
+    assert_eq!(jamo("ㅘa가"), "ㅗㅏaㄱㅏ");
+  }
+}
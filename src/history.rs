@@ -0,0 +1,104 @@
+//! Query history of the finder, persisted per source.
+//!
+//! Each source (e.g. `find` or `pick`) has a file in the history directory,
+//! which contains queries line by line from the oldest.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of queries kept in a history
+const MAX_ENTRIES: usize = 1000;
+
+/// Queries from the oldest to the latest
+#[derive(Clone, Default)]
+pub struct History {
+  path: Option<PathBuf>,
+  entries: Vec<String>,
+}
+
+impl History {
+  /// History only in memory, which is not saved
+  pub fn new(entries: Vec<String>) -> Self {
+    Self {
+      path: None,
+      entries,
+    }
+  }
+
+  /// Load the history of the source in the directory.
+  /// A missing file is an empty history.
+  pub fn load(dir: impl AsRef<Path>, source: &str) -> Self {
+    let path = dir.as_ref().join(source);
+    let entries = fs::read_to_string(&path)
+      .map(|s| {
+        s.lines()
+          .filter(|l| !l.is_empty())
+          .map(String::from)
+          .collect()
+      })
+      .unwrap_or_default();
+    Self {
+      path: Some(path),
+      entries,
+    }
+  }
+
+  pub fn entries(&self) -> &[String] {
+    &self.entries
+  }
+
+  /// Add the query as the latest, removing the same older one
+  pub fn add(&mut self, query: &str) {
+    let query = query.trim();
+    if query.is_empty() || query.contains('\n') {
+      return;
+    }
+    self.entries.retain(|e| e != query);
+    self.entries.push(query.to_string());
+    let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+    self.entries.drain(..excess);
+  }
+
+  /// Save into the file, if the history is loaded from a file
+  pub fn save(&self) -> std::io::Result<()> {
+    let Some(path) = &self.path else {
+      return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let mut content = self.entries.join("\n");
+    content.push('\n');
+    fs::write(path, content)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn add_moves_duplicates_to_latest() {
+    let mut history = History::new(vec!["a".into(), "b".into()]);
+    history.add("a");
+    history.add("  ");
+    history.add(" c ");
+    assert_eq!(history.entries(), ["b", "a", "c"]);
+  }
+
+  #[test]
+  fn save_and_load() {
+    let dir = std::env::temp_dir()
+      .join(format!("j2-history-test-{}", std::process::id()));
+    let mut history = History::load(&dir, "find");
+    assert!(history.entries().is_empty());
+    history.add("infra terraform prod");
+    history.add("src");
+    history.save().unwrap();
+
+    let history = History::load(&dir, "find");
+    assert_eq!(history.entries(), ["infra terraform prod", "src"]);
+    assert!(History::load(&dir, "pick").entries().is_empty());
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
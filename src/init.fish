@@ -0,0 +1,154 @@
+# luminkit's jump helper 2
+# Use with: j2 shell-init fish | source
+
+if test -z "$J2_IGNORE"
+  set -gx J2_IGNORE "$HOME/.J2_ignore"
+end
+if test -z "$J2_JONE_PATH"
+  set -gx J2_JONE_PATH "$HOME/.J2_jones"
+end
+if test -z "$J2_EDITOR"
+  set -gx J2_EDITOR "vi"
+end
+
+# Create functions
+set -g __J2 "<EXECUTABLE_PATH>"
+
+function __J2_copy
+  # Copy to clipboard with any available command, or OSC 52
+  if command -q pbcopy
+    printf '%s' $argv[1] | pbcopy
+  else if set -q WAYLAND_DISPLAY; and command -q wl-copy
+    printf '%s' $argv[1] | wl-copy
+  else if command -q xclip
+    printf '%s' $argv[1] | xclip -selection clipboard
+  else if command -q clip.exe
+    printf '%s' $argv[1] | clip.exe
+  else
+    printf '\e]52;c;%s\a' (printf '%s' $argv[1] | base64 | string join '')
+  end
+end
+function __J2_jump
+  # Find a path and run the action chosen in the finder.
+  # The first argument is the action for Enter.
+  set -l result ($__J2 find --print-action $argv[2..-1]); or return 1
+  set -l parts (string split -m 1 \t -- "$result")
+  if test (count $parts) -ne 2
+    # Maybe help
+    printf '%s\n' $result
+    return 1
+  end
+  set -l action $parts[1]
+  set -l p $parts[2]
+  test "$action" = accept; and set action $argv[1]
+  switch $action
+    case cd
+      echo "J2: cd to $p"
+      cd "$p"
+    case pushd
+      echo "J2: pushd to $p"
+      pushd "$p"
+    case edit
+      echo "J2: edit $p"
+      $J2_EDITOR "$p"
+    case copy
+      __J2_copy "$p"
+      echo "J2: copied $p"
+    case run
+      if test -z "$J2_RUN_COMMAND"
+        echo "J2: J2_RUN_COMMAND is not set"
+        return 1
+      end
+      echo "J2: $J2_RUN_COMMAND $p"
+      eval $J2_RUN_COMMAND (string escape -- $p)
+  end
+end
+
+function J
+  switch "$argv[1]"
+    case version
+      $__J2 --version
+    case find f
+      $__J2 find $argv[2..-1]
+    case cd c
+      __J2_jump cd $argv[2..-1]
+    case pushd push pus pu p
+      __J2_jump pushd $argv[2..-1]
+    case edit edi ed e
+      __J2_jump edit $argv[2..-1]
+    case clone C
+      $__J2 clone $argv[2..-1]
+    case jone-new new N
+      $__J2 jone-new $argv[2..-1]
+    case jone-list list l
+      $__J2 jone-list
+    case jone-sections sections s
+      $__J2 jone-sections $argv[2..-1]
+    case jone-note note n
+      set -l p ($__J2 jone-latest $argv[2..-1])
+      $J2_EDITOR "$p/README.md"
+    case '*'
+      printf '%s\n' "<INIT_HELP>"
+  end
+end
+function j
+  J cd $argv
+end
+function j!
+  J edit $argv
+end
+function j-+
+  J jone-new $argv
+end
+function j-
+  cd ($__J2 jone-latest $argv)
+end
+function j-!
+  $J2_EDITOR ($__J2 jone-latest $argv)
+end
+function j--
+  j-+ $argv
+  j- $argv
+end
+function j--!
+  j-+ $argv
+  j-! $argv
+end
+function j_
+  J jone-sections $argv
+end
+function j.
+  J jone-note $argv
+end
+
+# Key binding widgets: insert a fuzzy-found path at the cursor
+# J2_WIDGET_KEY finds in base paths, J2_WIDGET_CWD_KEY finds in the current
+# directory (files included). Set a key to "none" to disable it.
+function __J2_widget_insert
+  set -l p ($__J2 find $argv </dev/tty)
+  if test $status -eq 0; and test -n "$p"
+    commandline -i -- (string escape -- $p)
+  end
+  commandline -f repaint
+end
+function __J2_widget
+  __J2_widget_insert
+end
+function __J2_widget_cwd
+  __J2_widget_insert -f --dir "$PWD"
+end
+set -l __J2_key \ct
+test -n "$J2_WIDGET_KEY"; and set __J2_key $J2_WIDGET_KEY
+test "$__J2_key" != none; and bind $__J2_key __J2_widget
+set __J2_key \et
+test -n "$J2_WIDGET_CWD_KEY"; and set __J2_key $J2_WIDGET_CWD_KEY
+test "$__J2_key" != none; and bind $__J2_key __J2_widget_cwd
+
+complete -c J -f -a "version find cd pushd edit clone jone-new jone-list jone-sections jone-note"
+for f in j--! j-- j-+ j-! j- j_ j.
+  complete -c $f -f -a "($__J2 jone-list)"
+end
+
+# To initialize this for your shell permanently, add the following line to
+# ~/.config/fish/config.fish:
+# j2 shell-init fish | source
@@ -34,6 +34,56 @@ function __J2_find {
 	}
 }
 
+function __J2_jump {
+	# Find a path and run the action chosen in the finder.
+	# The first argument is the action for Enter.
+	param (
+		[string]$default,
+		[Parameter(ValueFromRemainingArguments=$true)]
+		[string[]]$args
+	)
+	$result = & $global:__J2 find --print-action @args
+	if ($LASTEXITCODE -ne 0) {
+		return
+	}
+	$parts = "$result" -split "`t", 2
+	if ($parts.Count -ne 2) {
+		# Maybe help
+		$result | ForEach-Object { Write-Output $_ }
+		return
+	}
+	$action, $p = $parts
+	if ($action -eq "accept") {
+		$action = $default
+	}
+	switch ($action) {
+		"cd" {
+			Write-Output "J2: cd to $p"
+			Set-Location $p
+		}
+		"pushd" {
+			Write-Output "J2: pushd to $p"
+			Push-Location $p
+		}
+		"edit" {
+			Write-Output "J2: edit $p"
+			& $env:J2_EDITOR $p
+		}
+		"copy" {
+			Set-Clipboard -Value $p
+			Write-Output "J2: copied $p"
+		}
+		"run" {
+			if (-not $env:J2_RUN_COMMAND) {
+				Write-Output "J2: J2_RUN_COMMAND is not set"
+				return
+			}
+			Write-Output "J2: $env:J2_RUN_COMMAND $p"
+			& $env:J2_RUN_COMMAND $p
+		}
+	}
+}
+
 function J {
 	param (
 		[string]$command,
@@ -48,25 +98,13 @@ function J {
 			__J2_find @args
 		}
 		"cd" {
-			$dirs = __J2_find @args
-			if ($dirs) {
-				Write-Output "J2: cd to $dirs"
-				Set-Location $dirs
-			}
+			__J2_jump "cd" @args
 		}
 		"pushd" {
-			$dirs = __J2_find @args
-			if ($dirs) {
-				Write-Output "J2: pushd to $dirs"
-				Push-Location $dirs
-			}
+			__J2_jump "pushd" @args
 		}
 		"edit" {
-			$dirs = __J2_find @args
-			if ($dirs) {
-				Write-Output "J2: edit $dirs"
-				& $env:J2_EDITOR $dirs
-			}
+			__J2_jump "edit" @args
 		}
 		"clone" {
 			& $global:__J2 clone @args
@@ -90,11 +128,7 @@ function J {
 "@ | Write-Output
 		}
 		default {
-			$dirs = __J2_find $command @args
-			if ($dirs) {
-				Write-Output "J2: cd to $dirs"
-				Set-Location $dirs
-			}
+			__J2_jump "cd" $command @args
 		}
 	}
 }
@@ -167,6 +201,38 @@ function j. {
 	J "jone-note" @args
 }
 
+# Key binding widgets: insert a fuzzy-found path at the cursor
+# J2_WIDGET_KEY finds in base paths, J2_WIDGET_CWD_KEY finds in the current
+# directory (files included). Set a key to "none" to disable it.
+function __J2_widget_insert {
+	param (
+		[Parameter(ValueFromRemainingArguments=$true)]
+		[string[]]$args
+	)
+	$p = & $global:__J2 find @args
+	if ($LASTEXITCODE -eq 0 -and $p) {
+		$quoted = "'" + ($p -replace "'", "''") + "'"
+		[Microsoft.PowerShell.PSConsoleReadLine]::Insert($quoted)
+	}
+	[Microsoft.PowerShell.PSConsoleReadLine]::InvokePrompt()
+}
+
+if (Get-Module -Name PSReadLine) {
+	$__J2_key = if ($env:J2_WIDGET_KEY) { $env:J2_WIDGET_KEY } else { "Ctrl+t" }
+	if ($__J2_key -ne "none") {
+		Set-PSReadLineKeyHandler -Chord $__J2_key -BriefDescription "J2 find" -ScriptBlock {
+			__J2_widget_insert
+		}
+	}
+	$__J2_key = if ($env:J2_WIDGET_CWD_KEY) { $env:J2_WIDGET_CWD_KEY } else { "Alt+t" }
+	if ($__J2_key -ne "none") {
+		Set-PSReadLineKeyHandler -Chord $__J2_key -BriefDescription "J2 find in current directory" -ScriptBlock {
+			__J2_widget_insert -f --dir "$PWD"
+		}
+	}
+	Remove-Variable __J2_key
+}
+
 # To initialize this for your shell, you should add the script to your PowerShell profile script.
 # For example, execute the following command:
 # Add-Content $PROFILE $(j2 shell-init pwsh)
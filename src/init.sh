@@ -30,6 +30,65 @@ __J2_find() {
 		echo ${dirs[1]}
 	fi
 }
+__J2_copy() {
+	# Copy to clipboard with any available command, or OSC 52
+	if command -v pbcopy > /dev/null 2>&1; then
+		printf '%s' "$1" | pbcopy
+	elif [ -n "$WAYLAND_DISPLAY" ] && command -v wl-copy > /dev/null 2>&1; then
+		printf '%s' "$1" | wl-copy
+	elif command -v xclip > /dev/null 2>&1; then
+		printf '%s' "$1" | xclip -selection clipboard
+	elif command -v clip.exe > /dev/null 2>&1; then
+		printf '%s' "$1" | clip.exe
+	else
+		printf '\033]52;c;%s\a' "$(printf '%s' "$1" | base64 | tr -d '\n')"
+	fi
+}
+__J2_jump() {
+	# Find a path and run the action chosen in the finder.
+	# The first argument is the action for Enter.
+	local result action p
+	result="$($__J2 find --print-action ${@:2})"
+	if [ $? -ne 0 ]; then
+		return 1
+	fi
+	action="${result%%$'\t'*}"
+	p="${result#*$'\t'}"
+	if [ "$action" = accept ]; then
+		action="$1"
+	fi
+	case "$action" in
+		cd)
+			echo "J2: cd to $p"
+			cd "$p"
+			;;
+		pushd)
+			echo "J2: pushd to $p"
+			pushd "$p"
+			;;
+		edit)
+			echo "J2: edit $p"
+			$J2_EDITOR "$p"
+			;;
+		copy)
+			__J2_copy "$p"
+			echo "J2: copied $p"
+			;;
+		run)
+			if [ -z "$J2_RUN_COMMAND" ]; then
+				echo "J2: J2_RUN_COMMAND is not set"
+				return 1
+			fi
+			echo "J2: $J2_RUN_COMMAND $p"
+			$J2_RUN_COMMAND "$p"
+			;;
+		*)
+			# Maybe help
+			echo "$result"
+			return 1
+			;;
+	esac
+}
 J() {
   case "$1" in
     version)
@@ -40,27 +99,15 @@ J() {
       ;;
     cd|c)
       # Change directory
-      dirs=$(__J2_find ${@:2})
-      if [ $? -eq 0 ]; then
-        echo "J2: cd to $dirs"
-        cd "$dirs"
-      fi
+      __J2_jump cd ${@:2}
       ;;
     pushd|push|pus|pu|p)
       # Push directory
-      dirs=$(__J2_find ${@:2})
-      if [ $? -eq 0 ]; then
-        echo "J2: pushd to $dirs"
-        pushd "$dirs"
-      fi
+      __J2_jump pushd ${@:2}
       ;;
     edit|edi|ed|e)
       # Edit with default editor
-      dirs=$(__J2_find ${@:2})
-      if [ $? -eq 0 ]; then
-        echo "J2: edit $dirs"
-        $J2_EDITOR "$dirs"
-      fi
+      __J2_jump edit ${@:2}
       ;;
     clone|C)
       # Clone git repository
@@ -125,6 +172,56 @@ j_() {
 j.() {
   J jone-note $@
 }
+# Key binding widgets: insert a fuzzy-found path at the cursor
+# J2_WIDGET_KEY finds in base paths, J2_WIDGET_CWD_KEY finds in the current
+# directory (files included). Set a key to "none" to disable it.
+# Key bindings need line editing, which only interactive shells have.
+if [[ $- == *i* ]] && [ -n "$ZSH_VERSION" ]; then
+  __J2_widget() {
+    local p
+    p="$($__J2 find < /dev/tty)"
+    if [ $? -eq 0 ] && [ -n "$p" ]; then
+      LBUFFER="${LBUFFER}${(q)p}"
+    fi
+    zle reset-prompt
+  }
+  __J2_widget_cwd() {
+    local p
+    p="$($__J2 find -f --dir "$PWD" < /dev/tty)"
+    if [ $? -eq 0 ] && [ -n "$p" ]; then
+      LBUFFER="${LBUFFER}${(q)p}"
+    fi
+    zle reset-prompt
+  }
+  zle -N __J2_widget
+  zle -N __J2_widget_cwd
+  __J2_key="${J2_WIDGET_KEY:-^T}"
+  [ "$__J2_key" != none ] && bindkey "$__J2_key" __J2_widget
+  __J2_key="${J2_WIDGET_CWD_KEY:-\\et}"
+  [ "$__J2_key" != none ] && bindkey "$__J2_key" __J2_widget_cwd
+  unset __J2_key
+elif [[ $- == *i* ]] && [ -n "$BASH_VERSION" ]; then
+  __J2_widget_insert() {
+    local p q
+    p="$($__J2 find "$@")"
+    if [ $? -eq 0 ] && [ -n "$p" ]; then
+      printf -v q '%q' "$p"
+      READLINE_LINE="${READLINE_LINE:0:$READLINE_POINT}$q${READLINE_LINE:$READLINE_POINT}"
+      READLINE_POINT=$((READLINE_POINT + ${#q}))
+    fi
+  }
+  __J2_widget() {
+    __J2_widget_insert
+  }
+  __J2_widget_cwd() {
+    __J2_widget_insert -f --dir "$PWD"
+  }
+  __J2_key="${J2_WIDGET_KEY:-\\C-t}"
+  [ "$__J2_key" != none ] && bind -x "\"$__J2_key\": __J2_widget"
+  __J2_key="${J2_WIDGET_CWD_KEY:-\\et}"
+  [ "$__J2_key" != none ] && bind -x "\"$__J2_key\": __J2_widget_cwd"
+  unset __J2_key
+fi
 complete -W "version find cd pushd edit clone jone-new jone-list jone-sections jone-note" J
 complete -F __J2_LIST "j--!"
 complete -F __J2_LIST "j--"
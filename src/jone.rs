@@ -0,0 +1,198 @@
+//! Jones (j-zones), sandboxes managed by tags and dated sections.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::section::{JoneSection, JoneSectionBase};
+
+/// Name of the jone without any tag
+pub const EMPTY_JONE_NAME: &str = "_";
+
+/// Convert to lowercase and replace whitespaces into underscores
+pub fn canonicalize_jone_name(name: &str) -> String {
+  let mut result = String::new();
+  for c in name.chars() {
+    if c.is_whitespace() {
+      result.push('_');
+    } else {
+      result.push(c.to_lowercase().next().unwrap());
+    }
+  }
+  result
+}
+
+/// Directory which stores jones
+pub struct JoneStore {
+  path: PathBuf,
+}
+
+impl JoneStore {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self { path: path.into() }
+  }
+
+  pub fn from_config(config: &Config) -> Self {
+    Self::new(&config.jones_path)
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Path of the jone directory
+  pub fn jone_path(&self, name: &str) -> PathBuf {
+    self.path.join(canonicalize_jone_name(name))
+  }
+
+  /// Path of the section directory in the jone
+  pub fn section_path(&self, name: &str, section: &JoneSection) -> PathBuf {
+    self.jone_path(name).join(section.to_string())
+  }
+
+  /// Names of all jones
+  pub fn list(&self) -> Vec<String> {
+    let mut names = vec![];
+    if let Ok(entries) = self.path.read_dir() {
+      for entry in entries {
+        let file_name =
+          entry.ok().and_then(|e| e.file_name().into_string().ok());
+        if file_name.is_none() {
+          continue;
+        }
+        let file_name = file_name.unwrap();
+        if file_name.starts_with(".") {
+          continue;
+        }
+        names.push(file_name);
+      }
+    }
+    names
+  }
+
+  /// Create a new section in the jone
+  pub fn create(&self, name: &str) -> io::Result<JoneSection> {
+    // Make directory
+    fs::create_dir_all(self.jone_path(name))?;
+    // Create jone file
+    let mut section = JoneSection::gen();
+    section.base = JoneSectionBase::Base10;
+    fs::create_dir(self.section_path(name, &section))?;
+    Ok(section)
+  }
+
+  /// Sections in the jone, from the latest
+  pub fn sections(&self, name: &str) -> Vec<JoneSection> {
+    // Read jone directories
+    let entries = self.jone_path(name).read_dir();
+    if entries.is_err() {
+      return vec![];
+    }
+    let entries = entries.unwrap();
+    let mut list = vec![];
+    for entry in entries {
+      if entry.is_err() {
+        continue;
+      }
+      let entry = entry.unwrap();
+      let file_name = entry.file_name().into_string();
+      if file_name.is_err() {
+        continue;
+      }
+      let file_name = file_name.unwrap();
+      let created = entry.metadata().and_then(|m| m.created()).ok();
+      if let Some(section) = JoneSection::from_str(file_name.as_str(), created)
+      {
+        list.push(section);
+      }
+    }
+    list.sort_by(|a, b| b.cmp(a));
+    list
+  }
+
+  /// The latest section in the jone
+  pub fn latest(&self, name: &str) -> Option<JoneSection> {
+    self.sections(name).into_iter().next()
+  }
+
+  /// Title of the section note, which is the first non-empty line
+  /// of README.md without markdown heading marks
+  pub fn note_title(
+    &self,
+    name: &str,
+    section: &JoneSection,
+  ) -> Option<String> {
+    let note =
+      fs::read_to_string(self.section_path(name, section).join("README.md"))
+        .ok()?;
+    note
+      .lines()
+      .map(strip_heading_marks)
+      .find(|line| !line.is_empty())
+      .map(|line| line.to_string())
+  }
+}
+
+/// Strip markdown heading marks, such as `## Title ##`,
+/// keeping closing `#`s not after a space, such as in `C#`
+fn strip_heading_marks(line: &str) -> &str {
+  let line = line.trim();
+  let Some(title) = line.strip_prefix('#') else {
+    return line;
+  };
+  let title = title.trim_start_matches('#').trim();
+  let open = title.trim_end_matches('#');
+  if open.is_empty() || open.ends_with(' ') {
+    open.trim_end()
+  } else {
+    title
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sections_are_created_and_listed_from_the_latest() {
+    let dir =
+      std::env::temp_dir().join(format!("j2-jone-test-{}", std::process::id()));
+    let store = JoneStore::new(&dir);
+    assert!(store.latest("My Jone").is_none());
+
+    let created = store.create("My Jone").unwrap();
+    assert!(store.section_path("My Jone", &created).is_dir());
+    assert_eq!(store.list(), ["my_jone"]);
+
+    // Sections of later dates come first
+    for name in ["990101-0000", "990102-0000", "not-a-section"] {
+      fs::create_dir(dir.join("my_jone").join(name)).unwrap();
+    }
+    let sections: Vec<String> = store
+      .sections("my jone")
+      .iter()
+      .map(|s| s.to_string())
+      .collect();
+    assert_eq!(sections.len(), 3);
+    assert_eq!(sections[..2], ["990102-0000", "990101-0000"]);
+    assert!(sections.contains(&created.to_string()));
+
+    let latest = store.latest("my jone").unwrap();
+    assert_eq!(latest.to_string(), "990102-0000");
+    assert_eq!(store.note_title("my jone", &latest), None);
+    assert_eq!(strip_heading_marks("# Notes on C#"), "Notes on C#");
+    assert_eq!(strip_heading_marks("plain #1"), "plain #1");
+    let note = "\n## Deploy notes ##\n\nbody\n";
+    fs::write(
+      store.section_path("my jone", &latest).join("README.md"),
+      note,
+    )
+    .unwrap();
+    assert_eq!(
+      store.note_title("my jone", &latest).as_deref(),
+      Some("Deploy notes")
+    );
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
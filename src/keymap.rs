@@ -0,0 +1,313 @@
+//! Key bindings of the finder.
+//!
+//! A keymap spec is a comma-separated list of `<KEY>:<ACTION>`,
+//! such as `ctrl-w:delete-word,alt-enter:accept:pushd`.
+//! Keys are names (`enter`, `up`, `page-down`, `f1`, ...) or characters,
+//! with optional `ctrl-`, `alt-` and `shift-` prefixes.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Action run by a key in the finder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+  /// Do nothing
+  Ignore,
+  /// Quit without selection
+  Abort,
+  /// Accept the selected item
+  Accept,
+  /// Accept the selected item with the named action
+  AcceptWith(String),
+  /// Move the selection up (to worse matches)
+  Up,
+  /// Move the selection down (to better matches)
+  Down,
+  PageUp,
+  PageDown,
+  /// Select the best match
+  First,
+  /// Select the worst match
+  Last,
+  BackwardChar,
+  ForwardChar,
+  BackwardWord,
+  ForwardWord,
+  BeginningOfLine,
+  EndOfLine,
+  BackwardDeleteChar,
+  DeleteChar,
+  /// Delete the word before the cursor
+  DeleteWord,
+  /// Delete from the cursor to the end of the query
+  KillLine,
+  ClearQuery,
+  /// Toggle (non-directory) files of the source
+  ToggleFiles,
+  /// Toggle hidden files of the source
+  ToggleHidden,
+  /// Restrict the source to the next base path
+  CycleBase,
+  /// Switch to the next matching mode
+  CycleMode,
+  /// Replace the query with the previous one in the history
+  PreviousHistory,
+  /// Replace the query with the next one in the history
+  NextHistory,
+  /// Replace the query with the previous one containing the typed query
+  SearchHistory,
+}
+
+impl Action {
+  pub fn from_name(name: &str) -> Option<Self> {
+    if let Some(action) = name.strip_prefix("accept:") {
+      return Some(Action::AcceptWith(action.to_string()));
+    }
+    Some(match name {
+      "ignore" => Action::Ignore,
+      "abort" => Action::Abort,
+      "accept" => Action::Accept,
+      "up" => Action::Up,
+      "down" => Action::Down,
+      "page-up" => Action::PageUp,
+      "page-down" => Action::PageDown,
+      "first" => Action::First,
+      "last" => Action::Last,
+      "backward-char" => Action::BackwardChar,
+      "forward-char" => Action::ForwardChar,
+      "backward-word" => Action::BackwardWord,
+      "forward-word" => Action::ForwardWord,
+      "beginning-of-line" => Action::BeginningOfLine,
+      "end-of-line" => Action::EndOfLine,
+      "backward-delete-char" => Action::BackwardDeleteChar,
+      "delete-char" => Action::DeleteChar,
+      "delete-word" => Action::DeleteWord,
+      "kill-line" => Action::KillLine,
+      "clear-query" => Action::ClearQuery,
+      "toggle-files" => Action::ToggleFiles,
+      "toggle-hidden" => Action::ToggleHidden,
+      "cycle-base" => Action::CycleBase,
+      "cycle-mode" => Action::CycleMode,
+      "previous-history" => Action::PreviousHistory,
+      "next-history" => Action::NextHistory,
+      "search-history" => Action::SearchHistory,
+      _ => return None,
+    })
+  }
+}
+
+/// Parse a key such as `ctrl-w`, `alt-enter` or `page-up`
+pub fn parse_key(s: &str) -> Option<(KeyCode, KeyModifiers)> {
+  let mut modifiers = KeyModifiers::empty();
+  let mut rest = s;
+  loop {
+    let lower = rest.to_ascii_lowercase();
+    if lower.starts_with("ctrl-") && rest.len() > 5 {
+      modifiers |= KeyModifiers::CONTROL;
+      rest = &rest[5..];
+    } else if lower.starts_with("alt-") && rest.len() > 4 {
+      modifiers |= KeyModifiers::ALT;
+      rest = &rest[4..];
+    } else if lower.starts_with("shift-") && rest.len() > 6 {
+      modifiers |= KeyModifiers::SHIFT;
+      rest = &rest[6..];
+    } else {
+      break;
+    }
+  }
+
+  let mut chars = rest.chars();
+  if let (Some(c), None) = (chars.next(), chars.next()) {
+    return Some((KeyCode::Char(c), modifiers));
+  }
+
+  let code = match rest.to_ascii_lowercase().as_str() {
+    "enter" | "return" => KeyCode::Enter,
+    "esc" | "escape" => KeyCode::Esc,
+    "tab" => KeyCode::Tab,
+    "btab" | "backtab" => KeyCode::BackTab,
+    "space" => KeyCode::Char(' '),
+    "backspace" | "bspace" => KeyCode::Backspace,
+    "delete" | "del" => KeyCode::Delete,
+    "insert" => KeyCode::Insert,
+    "up" => KeyCode::Up,
+    "down" => KeyCode::Down,
+    "left" => KeyCode::Left,
+    "right" => KeyCode::Right,
+    "home" => KeyCode::Home,
+    "end" => KeyCode::End,
+    "page-up" | "pgup" => KeyCode::PageUp,
+    "page-down" | "pgdn" => KeyCode::PageDown,
+    f if f.starts_with('f') => KeyCode::F(f[1..].parse().ok()?),
+    _ => return None,
+  };
+  Some((code, modifiers))
+}
+
+/// Normalize modifiers, because characters may come with SHIFT
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> KeyModifiers {
+  match code {
+    KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+    _ => modifiers,
+  }
+}
+
+/// Bindings from keys to actions
+#[derive(Clone)]
+pub struct Keymap {
+  bindings: Vec<(KeyCode, KeyModifiers, Action)>,
+}
+
+impl Default for Keymap {
+  /// Emacs-like default bindings
+  fn default() -> Self {
+    let mut keymap = Self::empty();
+    for (key, action) in [
+      ("esc", "abort"),
+      ("ctrl-c", "abort"),
+      ("ctrl-g", "abort"),
+      ("ctrl-q", "abort"),
+      ("enter", "accept"),
+      ("up", "up"),
+      ("ctrl-p", "up"),
+      ("alt-k", "up"),
+      ("down", "down"),
+      ("ctrl-n", "down"),
+      ("alt-j", "down"),
+      ("page-up", "page-up"),
+      ("page-down", "page-down"),
+      ("ctrl-home", "first"),
+      ("alt-<", "first"),
+      ("ctrl-end", "last"),
+      ("alt->", "last"),
+      ("left", "backward-char"),
+      ("ctrl-b", "backward-char"),
+      ("alt-h", "backward-char"),
+      ("right", "forward-char"),
+      ("ctrl-f", "forward-char"),
+      ("alt-l", "forward-char"),
+      ("alt-b", "backward-word"),
+      ("alt-f", "forward-word"),
+      ("home", "beginning-of-line"),
+      ("ctrl-a", "beginning-of-line"),
+      ("end", "end-of-line"),
+      ("ctrl-e", "end-of-line"),
+      ("backspace", "backward-delete-char"),
+      ("ctrl-h", "backward-delete-char"),
+      ("delete", "delete-char"),
+      ("ctrl-d", "delete-char"),
+      ("ctrl-w", "delete-word"),
+      ("alt-backspace", "delete-word"),
+      ("ctrl-k", "kill-line"),
+      ("ctrl-u", "clear-query"),
+      ("ctrl-t", "toggle-files"),
+      ("alt-.", "toggle-hidden"),
+      ("tab", "cycle-base"),
+      ("ctrl-s", "cycle-mode"),
+      ("alt-up", "previous-history"),
+      ("alt-down", "next-history"),
+      ("ctrl-r", "search-history"),
+    ] {
+      let (code, modifiers) = parse_key(key).unwrap();
+      keymap.bind(code, modifiers, Action::from_name(action).unwrap());
+    }
+    keymap
+  }
+}
+
+impl Keymap {
+  /// Keymap without any binding
+  pub fn empty() -> Self {
+    Self { bindings: vec![] }
+  }
+
+  /// Bind a key to an action, replacing the previous binding
+  pub fn bind(&mut self, code: KeyCode, modifiers: KeyModifiers, a: Action) {
+    let modifiers = normalize(code, modifiers);
+    self
+      .bindings
+      .retain(|(c, m, _)| !(*c == code && *m == modifiers));
+    self.bindings.push((code, modifiers, a));
+  }
+
+  /// Bind keys from a spec such as `ctrl-w:delete-word,ctrl-o:accept:edit`
+  pub fn bind_spec(&mut self, spec: &str) -> Result<(), String> {
+    for binding in spec.split(',').filter(|s| !s.trim().is_empty()) {
+      // Split at the first colon after the key, so `::accept` binds ':'
+      let split = binding.char_indices().skip(1).find(|(_, c)| *c == ':');
+      let (key, action) = match split {
+        Some((i, _)) => (binding[..i].trim(), binding[i + 1..].trim()),
+        None => return Err(format!("Invalid key binding '{}'", binding)),
+      };
+      let (code, modifiers) =
+        parse_key(key).ok_or_else(|| format!("Invalid key '{}'", key))?;
+      let action = Action::from_name(action)
+        .ok_or_else(|| format!("Invalid action '{}'", action))?;
+      self.bind(code, modifiers, action);
+    }
+    Ok(())
+  }
+
+  /// Find the action bound to the key event
+  pub fn get(&self, key: &KeyEvent) -> Option<&Action> {
+    let modifiers = normalize(key.code, key.modifiers);
+    self
+      .bindings
+      .iter()
+      .find(|(c, m, _)| *c == key.code && *m == modifiers)
+      .map(|(_, _, a)| a)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_keys() {
+    let ctrl = KeyModifiers::CONTROL;
+    assert_eq!(parse_key("ctrl-w"), Some((KeyCode::Char('w'), ctrl)));
+    assert_eq!(
+      parse_key("alt-enter"),
+      Some((KeyCode::Enter, KeyModifiers::ALT))
+    );
+    assert_eq!(
+      parse_key("page-down"),
+      Some((KeyCode::PageDown, KeyModifiers::empty()))
+    );
+    assert_eq!(
+      parse_key("-"),
+      Some((KeyCode::Char('-'), KeyModifiers::empty()))
+    );
+    assert_eq!(parse_key("ctrl--"), Some((KeyCode::Char('-'), ctrl)));
+    assert_eq!(
+      parse_key("f5"),
+      Some((KeyCode::F(5), KeyModifiers::empty()))
+    );
+    assert_eq!(parse_key("hyper-x"), None);
+  }
+
+  #[test]
+  fn bind_spec_overrides_defaults() {
+    let mut keymap = Keymap::default();
+    keymap
+      .bind_spec("ctrl-w:ignore, ctrl-o:accept:edit,::abort")
+      .unwrap();
+    let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+    assert_eq!(keymap.get(&ctrl('w')), Some(&Action::Ignore));
+    assert_eq!(
+      keymap.get(&ctrl('o')),
+      Some(&Action::AcceptWith("edit".to_string()))
+    );
+    assert_eq!(
+      keymap.get(&KeyEvent::from(KeyCode::Char(':'))),
+      Some(&Action::Abort)
+    );
+    assert_eq!(keymap.get(&ctrl('u')), Some(&Action::ClearQuery));
+
+    assert!(keymap.bind_spec("ctrl-w").is_err());
+    assert!(keymap.bind_spec("ctrl-w:fly").is_err());
+    // Marks are not supported, as the finder selects a single item
+    assert!(keymap.bind_spec("tab:toggle").is_err());
+    assert!(keymap.bind_spec("hyper-w:abort").is_err());
+  }
+}
@@ -1,4 +1,28 @@
+//! lumiknit's jump utility.
+//!
+//! The library exposes the fuzzy finder and jone logic used by the `j2`
+//! binary, so they can be embedded without shelling out.
+//!
+//! - [`ui_finder::Finder`]: Fuzzy finder TUI over any [`ui_finder::Item`]
+//! - [`matcher::Mode`]: Matching modes, such as regexes and globs
+//! - [`history::History`]: Query history of the finder
+//! - [`walker::Walker`]: Directory walker gathering [`path::PathItem`]s
+//! - [`jone::JoneStore`]: Jones and their sections
+//! - [`config::Config`]: Configuration from environment variables
+
+pub mod config;
 pub mod fuzzy;
+mod hangul;
+pub mod history;
+pub mod jone;
+pub mod keymap;
+pub mod matcher;
+pub mod path;
+mod scorer;
+pub mod section;
+pub mod theme;
+pub mod ui_finder;
+pub mod walker;
 
 #[cfg(test)]
 mod tests {
@@ -8,14 +32,21 @@ mod tests {
     let target1 = "foobar";
     let target2 = "out-of-bound";
 
-    let qcs = query.to_string().chars().collect();
+    let qcs: Vec<char> = query.chars().collect();
     let mut ed = crate::fuzzy::EditDist::new();
     ed.update_query(&qcs);
-    let cost1 = ed.run(&target1.to_string());
-    let cost2 = ed.run(&target2.to_string());
+    let cost1 = ed.run(target1).unwrap();
+    let cost2 = ed.run(target2).unwrap();
 
-    println!("\n---");
-    println!("{} for {}: {:?}", target1, query, cost1);
-    println!("{} for {}: {:?}", target2, query, cost2);
+    // Word starts are preferred to a contiguous match in a word
+    assert!(
+      cost2 < cost1,
+      "{} for {}, {} for {}",
+      cost1,
+      target1,
+      cost2,
+      target2
+    );
+    assert!(ed.run("bo").is_none());
   }
 }
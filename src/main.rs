@@ -3,21 +3,27 @@
  * Version: 0.2.2 (241005)
  */
 
+use std::io;
 use std::process::{exit, Command};
-use std::{env, fs, vec};
+use std::{env, fs};
 
 pub mod cli;
-pub mod config;
-pub mod fuzzy;
-pub mod path;
-pub mod section;
+pub mod format;
 pub mod shell;
-pub mod ui_finder;
 
 use clap::Parser;
-use config::Config;
-use path::PathItem;
-use section::JoneSection;
+use format::{OutputFormat, Record};
+use j2::config::{Config, FinderConfig};
+use j2::fuzzy;
+use j2::history::History;
+use j2::jone::{JoneStore, EMPTY_JONE_NAME};
+use j2::keymap::Keymap;
+use j2::matcher::{Matcher, Mode};
+use j2::path::{self, PathItem};
+use j2::section::JoneSection;
+use j2::theme::Theme;
+use j2::ui_finder::{Finder, Item, Source, Toggle};
+use j2::walker::Walker;
 use shell::ShellType;
 
 fn get_executable_path(exe: &str) -> Option<String> {
@@ -28,185 +34,427 @@ fn get_executable_path(exe: &str) -> Option<String> {
     .and_then(|p| p.to_str().map(|s| s.to_string()))
 }
 
-fn gather_all_paths(
+fn path_walker(
   base: Vec<String>,
+  dir: Vec<String>,
   files: bool,
   all: bool,
-) -> Vec<path::PathItem> {
+) -> Walker {
   let config = Config::from_env();
 
-  let base_paths = if base.is_empty() {
+  let base_paths = if base.is_empty() && dir.is_empty() {
     config.find_base_paths.clone()
   } else {
     base
       .iter()
-      .map(|s| s.split(":").map(|s| s.to_string()))
-      .flatten()
+      .flat_map(|s| s.split(":").map(|s| s.to_string()))
+      .chain(dir)
       .collect()
   };
 
-  // Convert base paths to names
-  let base_paths = path::convert_base_paths_to_names(&base_paths);
+  Walker::new(base_paths)
+    .files(files)
+    .hidden(all)
+    .ignore_file(config.ignore_file_path)
+}
 
-  // Traverse all directories and gather paths
-  let paths = std::sync::Mutex::new(vec![]);
+/// Source of items which are already loaded,
+/// and reloaded from the inner source after toggles
+struct Preloaded<S> {
+  items: Option<Vec<PathItem>>,
+  source: S,
+}
 
-  for base in base_paths.into_iter() {
-    let mut builder = ignore::WalkBuilder::new(base.abs.clone());
-    builder.standard_filters(true).hidden(!all);
-    if let Some(p) = &config.ignore_file_path {
-      if let Some(_err) = builder.add_ignore(p) {
-        // eprintln!("Error to load ignore file({})\n{}", p, _err);
-      }
+impl<S: Source<PathItem>> Source<PathItem> for Preloaded<S> {
+  fn load(&mut self) -> Vec<PathItem> {
+    match self.items.take() {
+      Some(items) => items,
+      None => self.source.load(),
     }
-    builder.build_parallel().run(|| {
-      Box::new(|result| {
-        if let Ok(entry) = result {
-          let path = entry.path();
-          if path.is_dir() || files {
-            let mut paths = paths.lock().unwrap();
-            let abs = path.to_str().unwrap().to_string();
-            let displayed = if path.starts_with(&base.abs) {
-              base.displayed.clone() + ": " + &abs[base.abs.len()..].to_string()
-            } else {
-              abs.clone()
-            };
-            paths.push(PathItem { displayed, abs });
-            return ignore::WalkState::Continue;
-          }
-        }
-        ignore::WalkState::Skip
-      })
-    });
   }
 
-  // Destruct paths from mutex wrapper
-  paths.into_inner().unwrap()
+  fn toggle(&mut self, toggle: Toggle) -> bool {
+    self.source.toggle(toggle)
+  }
+
+  fn status(&self) -> String {
+    self.source.status()
+  }
+}
+
+fn path_record(cost: Option<u32>, path: &path::PathItem) -> Record {
+  Record::new()
+    .str("path", &path.abs)
+    .str("displayed", &path.displayed)
+    .opt_str("base", path.base.as_deref())
+    .opt_num("cost", cost.map(u64::from))
+}
+
+/// Action printed for items accepted without any specific action
+const ACCEPT_ACTION: &str = "accept";
+
+/// Default keys to accept with an action, when actions are printed
+const ACTION_KEYMAP: &str =
+  "ctrl-o:accept:edit,ctrl-y:accept:copy,alt-enter:accept:pushd,ctrl-x:accept:run";
+
+/// Printer of selected paths
+#[derive(Clone, Copy)]
+struct PathPrinter {
+  format: OutputFormat,
+  print_action: bool,
+}
+
+impl PathPrinter {
+  fn print(&self, cost: u32, path: &path::PathItem, action: Option<&str>) {
+    let action = action.unwrap_or(ACCEPT_ACTION);
+    let record = path_record(Some(cost), path);
+    if !self.print_action {
+      println!("{}", record.format(self.format));
+    } else if self.format == OutputFormat::Plain {
+      println!("{}\t{}", action, path.abs);
+    } else {
+      println!("{}", record.str("action", action).format(self.format));
+    }
+  }
 }
 
-fn cmd_find_first(paths: &Vec<path::PathItem>, query: &String) {
+/// Fuzzy matcher of the query with options
+fn query_matcher(query: &str, args: &cli::SelectArgs) -> fuzzy::EditDist {
+  let mut weights = fuzzy::Weights::default();
+  if let Some(spec) = &FinderConfig::from_env().weights {
+    if let Err(e) = weights.apply_spec(spec) {
+      eprintln!("Invalid J2_WEIGHTS: {}", e);
+      exit(1);
+    }
+  }
   let mut ed = fuzzy::EditDist::new();
-  ed.update_query(&query.chars().collect());
-  let mut min_dist = std::u32::MAX;
-  let mut min_path = None;
-  for path in paths {
-    if let Some(cost) = ed.run(&path.displayed) {
-      if cost < min_dist {
-        min_dist = cost;
-        min_path = Some(path);
+  ed.weights(weights)
+    .smart_case(args.smart_case)
+    .nfkc(args.nfkc)
+    .ignore_diacritics(args.ignore_diacritics)
+    .jamo(args.jamo)
+    .typos(args.typos)
+    .update_query(&query.chars().collect::<Vec<_>>());
+  ed
+}
+
+/// Matcher of the query in the mode of the arguments,
+/// exiting if the query is an invalid pattern
+fn mode_matcher(query: &str, args: &cli::SelectArgs) -> Box<dyn Matcher> {
+  let mut matcher = args.mode.matcher(&query_matcher(query, args));
+  matcher.update_query(&query.chars().collect::<Vec<_>>());
+  if let Some(e) = matcher.error() {
+    eprintln!("{}", e);
+    exit(1);
+  }
+  matcher
+}
+
+/// Matches of paths in their order, allowing typos only if nothing matches
+fn score_paths<'a>(
+  paths: &'a [path::PathItem],
+  ed: &mut dyn Matcher,
+) -> Vec<(u32, &'a path::PathItem)> {
+  let scored: Vec<_> = paths
+    .iter()
+    .filter_map(|path| {
+      // Skip paths lacking some query characters
+      if !ed.may_match(path.mask()) {
+        return None;
       }
-    }
+      ed.run(&path.displayed, path.prefix_len())
+        .map(|cost| (cost, path))
+    })
+    .collect();
+  if !scored.is_empty() || !ed.tolerates_typos() {
+    return scored;
   }
-  if let Some(min_path) = min_path {
-    println!("{}", min_path.abs);
+  paths
+    .iter()
+    .filter_map(|path| {
+      ed.run_tolerant(&path.displayed, path.prefix_len())
+        .map(|c| (c, path))
+    })
+    .collect()
+}
+
+fn cmd_find_first(
+  paths: &[path::PathItem],
+  mut ed: Box<dyn Matcher>,
+  printer: PathPrinter,
+) {
+  let best = score_paths(paths, ed.as_mut())
+    .into_iter()
+    .min_by(compare_matches);
+  if let Some((cost, path)) = best {
+    printer.print(cost, path, None);
   } else {
     exit(1);
   }
 }
 
-fn cmd_find_interactively(paths: &Vec<path::PathItem>, query: &String) {
-  let result = ui_finder::run(paths.to_vec(), query);
-  if let Some(result) = result {
-    println!("{}", result);
-  } else {
+/// Order of matches by costs, then by `fuzzy::tie_key` of paths
+fn compare_matches(
+  (a_cost, a): &(u32, &path::PathItem),
+  (b_cost, b): &(u32, &path::PathItem),
+) -> std::cmp::Ordering {
+  a_cost.cmp(b_cost).then_with(|| {
+    fuzzy::tie_key(&a.displayed).cmp(&fuzzy::tie_key(&b.displayed))
+  })
+}
+
+fn rank_paths<'a>(
+  paths: &'a [path::PathItem],
+  ed: &mut dyn Matcher,
+) -> Vec<(u32, &'a path::PathItem)> {
+  let mut ranked = score_paths(paths, ed);
+  ranked.sort_by(compare_matches);
+  ranked
+}
+
+fn cmd_find_filter(
+  paths: &[path::PathItem],
+  mut ed: Box<dyn Matcher>,
+  limit: Option<usize>,
+  with_scores: bool,
+  format: OutputFormat,
+) {
+  let ranked = rank_paths(paths, ed.as_mut());
+  if ranked.is_empty() {
     exit(1);
   }
+  for (cost, path) in ranked.iter().take(limit.unwrap_or(usize::MAX)) {
+    if with_scores && format == OutputFormat::Plain {
+      println!("{}\t{}", cost, path.abs);
+    } else {
+      println!("{}", path_record(Some(*cost), path).format(format));
+    }
+  }
 }
 
-fn clone(config: &Config, repo_url: &str, depth: Option<u32>) {
-  // Split repo_url by protocal
-  let url_clone = repo_url;
-  let splitted: Vec<&str> = url_clone.split("://").collect();
-  if splitted.len() != 2 {
-    println!("Invalid repo url: {}", repo_url);
-    exit(1)
+/// Text with matched characters in brackets, such as `src/[fo]o`
+fn mark_matched(text: &str, matched: &[bool]) -> String {
+  let mut marked = String::new();
+  let mut in_match = false;
+  for (c, m) in text.chars().zip(matched) {
+    if *m != in_match {
+      marked.push(if *m { '[' } else { ']' });
+      in_match = *m;
+    }
+    marked.push(c);
   }
-  // Mkdir
-  let path = std::path::Path::new(&config.repos_path).join(splitted[1]);
-  fs::create_dir_all(path.clone()).expect("Failed to create directory");
-  // Clone repo
-  let mut cmd = Command::new("git");
-  let mut cmd = cmd.arg("clone").arg(repo_url).arg(path.to_str().unwrap());
-  if let Some(d) = depth {
-    cmd = cmd.arg(format!("--depth={}", d));
+  if in_match {
+    marked.push(']');
   }
-  cmd.spawn().expect("Failed to clone repo").wait().unwrap();
+  marked
 }
 
-fn jone_list(config: &Config) {
-  // Read jone directories
-  let path = std::path::Path::new(&config.jones_path);
-  if let Ok(entries) = path.read_dir() {
-    for entry in entries {
-      let file_name = entry.ok().and_then(|e| e.file_name().into_string().ok());
-      if file_name.is_none() {
-        continue;
+fn cmd_find_explain(
+  paths: &[path::PathItem],
+  mut ed: fuzzy::EditDist,
+  limit: Option<usize>,
+  format: OutputFormat,
+) {
+  let ranked = rank_paths(paths, &mut ed.clone());
+  if ranked.is_empty() {
+    exit(1);
+  }
+  for (cost, path) in ranked.iter().take(limit.unwrap_or(10)) {
+    let explanation = ed.explain(&path.displayed);
+    if format != OutputFormat::Plain {
+      let mut record = path_record(Some(*cost), path);
+      if let Some(e) = &explanation {
+        record =
+          record.str("matched", &mark_matched(&path.displayed, &e.matched));
+        for (name, cost) in &e.costs {
+          record = record.num(name, u64::from(*cost));
+        }
       }
-      let file_name = file_name.unwrap();
-      if file_name.starts_with(".") {
-        continue;
+      println!("{}", record.format(format));
+      continue;
+    }
+    println!("{}\t{}", cost, path.abs);
+    match explanation {
+      Some(e) => {
+        println!("\t{}", mark_matched(&path.displayed, &e.matched));
+        let costs: Vec<String> = e
+          .costs
+          .iter()
+          .filter(|(_, cost)| *cost > 0)
+          .map(|(name, cost)| format!("{}={}", name, cost))
+          .collect();
+        println!("\t{}", costs.join(" "));
       }
-      println!("{}", file_name);
+      None => println!("\t(with typos)"),
     }
   }
 }
 
-const EMPTY_JONE_NAME: &str = "_";
-
-fn canonicalize_jone_name(name: &str) -> String {
-  // Convert to lowercase and replace whitespaces into underscores
-  let mut result = String::new();
-  for c in name.chars() {
-    if c.is_whitespace() {
-      result.push('_');
-    } else {
-      result.push(c.to_lowercase().next().unwrap());
+fn cmd_find_interactively(
+  source: impl Source<PathItem> + 'static,
+  query: &str,
+  printer: PathPrinter,
+  finder: &cli::FinderArgs,
+  matcher: fuzzy::EditDist,
+  mode: Mode,
+  history: &str,
+) {
+  let config = FinderConfig::from_env();
+  let mut keymap = Keymap::default();
+  if printer.print_action {
+    keymap.bind_spec(ACTION_KEYMAP).unwrap();
+  }
+  if let Some(spec) = &config.keymap {
+    if let Err(e) = keymap.bind_spec(spec) {
+      eprintln!("Invalid J2_KEYMAP: {}", e);
+      exit(1);
+    }
+  }
+  let mut theme = if config.no_color {
+    Theme::no_color()
+  } else {
+    Theme::default()
+  };
+  for (name, spec) in [("J2_COLOR", &config.color), ("--color", &finder.color)]
+  {
+    if let Some(spec) = spec {
+      if let Err(e) = theme.apply_spec(spec) {
+        eprintln!("Invalid {}: {}", name, e);
+        exit(1);
+      }
+    }
+  }
+  let mut finder_ui = Finder::new(source);
+  if let Some(dir) = &config.history_dir {
+    finder_ui = finder_ui.history(History::load(dir, history));
+  }
+  let result = finder_ui
+    .query(query)
+    .keymap(keymap)
+    .theme(theme)
+    .matcher(matcher)
+    .mode(mode)
+    .height(finder.height())
+    .reverse(finder.reverse)
+    .mouse(!finder.no_mouse)
+    .run();
+  match result {
+    Ok(Some(sel)) => printer.print(sel.cost, &sel.item, sel.action.as_deref()),
+    Ok(None) => exit(1),
+    Err(e) => {
+      eprintln!("Failed to run the finder: {}", e);
+      exit(1);
     }
   }
-  result
 }
 
-fn jone_new(config: &Config, name: &str) -> String {
-  let name = canonicalize_jone_name(name);
-  let jone_path = std::path::Path::new(&config.jones_path).join(name);
-  // Make directory
-  fs::create_dir_all(&jone_path).expect("Failed to create directory");
-  // Create jone file
-  let section_name = JoneSection::gen().to_base10();
-  let section_path = jone_path.join(section_name);
-  fs::create_dir(std::path::Path::new(&section_path))
-    .expect("Failed to create directory");
-  section_path.to_str().unwrap().to_string()
+fn gather_stdin_lines() -> Vec<path::PathItem> {
+  io::stdin()
+    .lines()
+    .map_while(Result::ok)
+    .filter(|line| !line.is_empty())
+    .map(|line| PathItem::new(line.clone(), line, None))
+    .collect()
 }
 
-fn jone_section_list(config: &Config, name: &str) -> vec::Vec<JoneSection> {
-  let name = canonicalize_jone_name(name);
-  let jone_path = std::path::Path::new(&config.jones_path).join(name);
-  // Read jone directories
-  let path = std::path::Path::new(&jone_path);
-  let entries = path.read_dir();
-  if entries.is_err() {
-    return vec![];
+fn cmd_select<S: Source<PathItem> + 'static>(
+  mut source: S,
+  query: &str,
+  args: &cli::SelectArgs,
+  finder: &cli::FinderArgs,
+  history: &str,
+  format: OutputFormat,
+) {
+  let printer = PathPrinter {
+    format,
+    print_action: args.print_action,
+  };
+  if args.explain {
+    let ed = query_matcher(query, args);
+    return cmd_find_explain(&source.load(), ed, args.limit, format);
   }
-  let entries = entries.unwrap();
-  let mut list = vec![];
-  for entry in entries {
-    if entry.is_err() {
-      continue;
-    }
-    let entry = entry.unwrap();
-    let file_name = entry.file_name().into_string();
-    if file_name.is_err() {
-      continue;
+  if args.first {
+    let ed = mode_matcher(query, args);
+    return cmd_find_first(&source.load(), ed, printer);
+  }
+  if args.filter {
+    let paths = source.load();
+    return cmd_find_filter(
+      &paths,
+      mode_matcher(query, args),
+      args.limit,
+      args.with_scores,
+      format,
+    );
+  }
+  if args.select_1 || args.exit_0 {
+    let paths = source.load();
+    let ranked = rank_paths(&paths, mode_matcher(query, args).as_mut());
+    if ranked.is_empty() && args.exit_0 {
+      exit(1);
     }
-    let file_name = file_name.unwrap();
-    let created = entry.metadata().and_then(|m| m.created()).ok();
-    if let Some(section) = JoneSection::from_str(file_name.as_str(), created) {
-      list.push(section);
+    if ranked.len() == 1 && args.select_1 {
+      printer.print(ranked[0].0, ranked[0].1, None);
+      return;
     }
+    let source = Preloaded {
+      items: Some(paths),
+      source,
+    };
+    let matcher = query_matcher("", args);
+    return cmd_find_interactively(
+      source, query, printer, finder, matcher, args.mode, history,
+    );
+  }
+  let matcher = query_matcher("", args);
+  cmd_find_interactively(
+    source, query, printer, finder, matcher, args.mode, history,
+  );
+}
+
+fn clone(config: &Config, repo_url: &str, depth: Option<u32>) {
+  // Split repo_url by protocal
+  let url_clone = repo_url;
+  let splitted: Vec<&str> = url_clone.split("://").collect();
+  if splitted.len() != 2 {
+    println!("Invalid repo url: {}", repo_url);
+    exit(1)
+  }
+  // Mkdir
+  let path = std::path::Path::new(&config.repos_path).join(splitted[1]);
+  fs::create_dir_all(path.clone()).expect("Failed to create directory");
+  // Clone repo
+  let mut cmd = Command::new("git");
+  let mut cmd = cmd.arg("clone").arg(repo_url).arg(path.to_str().unwrap());
+  if let Some(d) = depth {
+    cmd = cmd.arg(format!("--depth={}", d));
   }
-  list.sort_by(|a, b| b.cmp(a));
-  list
+  cmd.spawn().expect("Failed to clone repo").wait().unwrap();
+}
+
+fn jone_section_record(
+  store: &JoneStore,
+  name: &str,
+  section: &JoneSection,
+) -> Record {
+  let path = store.section_path(name, section);
+  let created = section.sys_time.map(|time| {
+    let created: chrono::DateTime<chrono::Local> = time.into();
+    created.to_rfc3339()
+  });
+  Record::new()
+    .str("path", path.to_str().unwrap())
+    .str("jone", &j2::jone::canonicalize_jone_name(name))
+    .str("section", &section.to_string())
+    .str(
+      "date",
+      &format!(
+        "20{:02}-{:02}-{:02}",
+        section.year, section.month, section.day
+      ),
+    )
+    .opt_str("created", created.as_deref())
+    .opt_str("note_title", store.note_title(name, section).as_deref())
 }
 
 fn cmd_shell_init(shell: ShellType) {
@@ -215,6 +463,7 @@ fn cmd_shell_init(shell: ShellType) {
   let exe = get_executable_path(args[0].as_str()).unwrap_or(String::from("j2"));
   let script = match shell {
     ShellType::Sh => include_str!("init.sh"),
+    ShellType::Fish => include_str!("init.fish"),
     ShellType::Pwsh => include_str!("init.ps1"),
   };
   let s = script
@@ -223,52 +472,63 @@ fn cmd_shell_init(shell: ShellType) {
   println!("{}", s.trim());
 }
 
-fn cmd_clone(url: &String, depth: Option<u32>) {
+fn cmd_clone(url: &str, depth: Option<u32>) {
   let config = Config::from_env();
   clone(&config, url, depth);
 }
 
-fn cmd_jone_new(name: &String) {
-  let config = Config::from_env();
-  let p = jone_new(&config, name);
-  println!("{}", p);
+/// Create a new section in the jone, or exit on failure
+fn create_section(store: &JoneStore, name: &str) -> JoneSection {
+  store.create(name).unwrap_or_else(|e| {
+    eprintln!("Failed to create a section of '{}': {}", name, e);
+    exit(1);
+  })
 }
 
-fn cmd_jone_list() {
-  let config = Config::from_env();
-  jone_list(&config);
+fn cmd_jone_new(name: &str, format: OutputFormat) {
+  let store = JoneStore::from_config(&Config::from_env());
+  let section = create_section(&store, name);
+  let record = jone_section_record(&store, name, &section);
+  println!("{}", record.format(format));
 }
 
-fn cmd_jone_section_list(name: &String) {
-  let config = Config::from_env();
-  let list = jone_section_list(&config, name);
-  for section in list {
-    println!("{}", section.to_string());
+fn cmd_jone_list(format: OutputFormat) {
+  let store = JoneStore::from_config(&Config::from_env());
+  for name in store.list() {
+    let record = Record::new()
+      .str("name", &name)
+      .str("path", store.path().join(&name).to_str().unwrap());
+    println!("{}", record.format(format));
   }
 }
 
-fn cmd_jone_latest(name: &String) {
-  let config = Config::from_env();
-  let list = jone_section_list(&config, name);
-  if list.len() > 0 {
-    println!(
-      "{}",
-      std::path::Path::new(&config.jones_path)
-        .join(name)
-        .join(list[0].to_string())
-        .to_str()
-        .unwrap()
-    )
-  } else {
-    let p = jone_new(&config, name);
-    println!("{}", p);
+fn cmd_jone_section_list(name: &str, format: OutputFormat) {
+  let store = JoneStore::from_config(&Config::from_env());
+  for section in store.sections(name) {
+    if format == OutputFormat::Plain {
+      // Only section names, rather than paths
+      println!("{}", section);
+    } else {
+      let record = jone_section_record(&store, name, &section);
+      println!("{}", record.format(format));
+    }
   }
 }
 
-fn name_list_to_string(name: &Vec<String>, delimiter: &str) -> String {
+fn cmd_jone_latest(name: &str, format: OutputFormat) {
+  let store = JoneStore::from_config(&Config::from_env());
+  let section = match store.latest(name) {
+    Some(section) => section,
+    None => create_section(&store, name),
+  };
+  let record = jone_section_record(&store, name, &section);
+  println!("{}", record.format(format));
+}
+
+fn name_list_to_string(name: &[String], delimiter: &str) -> String {
   let joined = name.join(delimiter);
   let trimmed = joined.trim();
-  if trimmed.len() <= 0 {
+  if trimmed.is_empty() {
     EMPTY_JONE_NAME.to_string()
   } else {
     trimmed.to_string()
@@ -278,6 +538,7 @@ fn name_list_to_string(name: &Vec<String>, delimiter: &str) -> String {
 fn main() {
   // Parse command line arguments
   let parsed_command = cli::Cli::parse();
+  let format = parsed_command.format;
   match parsed_command.command {
     cli::Command::ShellInit { shell } => {
       let sh = if let Some(s) = shell {
@@ -285,7 +546,10 @@ fn main() {
         if let Some(parsed) = parsed {
           parsed
         } else {
-          eprintln!("Invalid shell type '{}', available options: sh, pwsh", s);
+          eprintln!(
+            "Invalid shell type '{}', available options: sh, fish, pwsh",
+            s
+          );
           exit(1);
         }
       } else {
@@ -296,28 +560,35 @@ fn main() {
     cli::Command::Find {
       query,
       base,
-      first,
+      dir,
+      select,
+      finder,
       files,
       all,
     } => {
-      let paths = gather_all_paths(base, files, all);
+      let walker = path_walker(base, dir, files, all);
       let query = query.join("");
-      if first {
-        cmd_find_first(&paths, &query);
-      } else {
-        cmd_find_interactively(&paths, &query);
-      }
+      cmd_select(walker, &query, &select, &finder, "find", format);
+    }
+    cli::Command::Pick {
+      query,
+      select,
+      finder,
+    } => {
+      let lines = gather_stdin_lines();
+      let query = query.join("");
+      cmd_select(lines, &query, &select, &finder, "pick", format);
     }
     cli::Command::Clone { url, depth } => cmd_clone(&url, depth),
-    cli::Command::JoneList => cmd_jone_list(),
+    cli::Command::JoneList => cmd_jone_list(format),
     cli::Command::JoneNew { name } => {
-      cmd_jone_new(&name_list_to_string(&name, " "));
+      cmd_jone_new(&name_list_to_string(&name, " "), format);
     }
     cli::Command::JoneSections { name } => {
-      cmd_jone_section_list(&name_list_to_string(&name, " "));
+      cmd_jone_section_list(&name_list_to_string(&name, " "), format);
     }
     cli::Command::JoneLatest { name } => {
-      cmd_jone_latest(&name_list_to_string(&name, " "));
+      cmd_jone_latest(&name_list_to_string(&name, " "), format);
     }
   }
 }
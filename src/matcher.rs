@@ -0,0 +1,571 @@
+//! Matching algorithms of queries, selected by modes.
+//!
+//! - `fuzzy`: [`fuzzy::EditDist`], where the query is a subsequence
+//! - `exact`: Substrings
+//! - `prefix`: Prefixes of the text or of path components
+//! - `regex`: Regular expressions, such as `^work/.*-api$`
+//! - `glob`: Globs, such as `*.rs`, matched to the basename if the glob
+//!   has no path separator
+//!
+//! Regexes and globs are matched to the path relative to the base path,
+//! which follows the prefix of `ui_finder::Item::prefix_len`.
+//!
+//! Except for `fuzzy`, the cost of a match is the number of unmatched
+//! characters, so shorter texts are ranked first.
+//! Matching is case-insensitive, or case-sensitive for queries with
+//! uppercase characters in smart-case mode.
+
+use std::fmt;
+use std::str::FromStr;
+
+use globset::GlobBuilder;
+use regex::{Regex, RegexBuilder};
+
+use crate::fuzzy::{self, EditDist};
+
+/// Matcher of a query, which is cloned into scoring threads
+pub trait Matcher: Send {
+  fn update_query(&mut self, query: &[char]);
+
+  /// Cost of the target, or None if it does not match.
+  /// The target starts with a prefix of `prefix_len` bytes,
+  /// such as the name of the base path.
+  fn run(&mut self, target: &str, prefix_len: usize) -> Option<u32>;
+
+  /// Positions of target characters to highlight
+  fn match_positions(&mut self, target: &str, prefix_len: usize) -> Vec<bool>;
+
+  /// Check if a target of the mask by `fuzzy::char_mask` may match
+  fn may_match(&self, _mask: u64) -> bool {
+    true
+  }
+
+  /// Whether appending the character to the query only removes matches,
+  /// so only the previous matches need to be scored again.
+  /// This does not hold for regexes such as `a|b` and globs such as `a*`.
+  fn narrows_on(&self, _query: &[char], _appended: char) -> bool {
+    false
+  }
+
+  /// Whether `run_tolerant` may match targets which `run` does not
+  fn tolerates_typos(&self) -> bool {
+    false
+  }
+
+  /// Cost of the target, allowing typos
+  fn run_tolerant(&mut self, target: &str, prefix_len: usize) -> Option<u32> {
+    self.run(target, prefix_len)
+  }
+
+  /// Error of the query, such as an invalid pattern, which matches nothing
+  fn error(&self) -> Option<&str> {
+    None
+  }
+
+  fn box_clone(&self) -> Box<dyn Matcher>;
+}
+
+impl Clone for Box<dyn Matcher> {
+  fn clone(&self) -> Self {
+    self.box_clone()
+  }
+}
+
+impl Matcher for EditDist {
+  fn update_query(&mut self, query: &[char]) {
+    EditDist::update_query(self, query);
+  }
+
+  fn run(&mut self, target: &str, _prefix_len: usize) -> Option<u32> {
+    EditDist::run(self, target)
+  }
+
+  fn match_positions(&mut self, target: &str, _prefix_len: usize) -> Vec<bool> {
+    EditDist::match_positions(self, target)
+  }
+
+  fn may_match(&self, mask: u64) -> bool {
+    EditDist::may_match(self, mask)
+  }
+
+  fn narrows_on(&self, _query: &[char], appended: char) -> bool {
+    EditDist::narrows_on(self, appended)
+  }
+
+  fn tolerates_typos(&self) -> bool {
+    self.max_typos() > 0
+  }
+
+  fn run_tolerant(&mut self, target: &str, _prefix_len: usize) -> Option<u32> {
+    EditDist::run_tolerant(self, target)
+  }
+
+  fn box_clone(&self) -> Box<dyn Matcher> {
+    Box::new(self.clone())
+  }
+}
+
+/// Matching mode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+  #[default]
+  Fuzzy,
+  Exact,
+  Prefix,
+  Regex,
+  Glob,
+}
+
+impl Mode {
+  const ALL: [Mode; 5] = [
+    Mode::Fuzzy,
+    Mode::Exact,
+    Mode::Prefix,
+    Mode::Regex,
+    Mode::Glob,
+  ];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Mode::Fuzzy => "fuzzy",
+      Mode::Exact => "exact",
+      Mode::Prefix => "prefix",
+      Mode::Regex => "regex",
+      Mode::Glob => "glob",
+    }
+  }
+
+  /// Next mode, to cycle modes in the finder
+  pub fn next(self) -> Self {
+    let i = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+    Self::ALL[(i + 1) % Self::ALL.len()]
+  }
+
+  /// Matcher of the mode, with options of the fuzzy matcher
+  pub fn matcher(self, fuzzy: &EditDist) -> Box<dyn Matcher> {
+    let smart_case = fuzzy.is_smart_case();
+    match self {
+      Mode::Fuzzy => Box::new(fuzzy.clone()),
+      Mode::Exact => Box::new(Substring::new(false, smart_case)),
+      Mode::Prefix => Box::new(Substring::new(true, smart_case)),
+      Mode::Regex => Box::new(RegexMatcher::new(smart_case)),
+      Mode::Glob => Box::new(GlobMatcher::new(smart_case)),
+    }
+  }
+}
+
+impl fmt::Display for Mode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+impl FromStr for Mode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::ALL
+      .into_iter()
+      .find(|m| m.name() == s)
+      .ok_or_else(|| format!("Invalid mode '{}'", s))
+  }
+}
+
+/// Whether to match the query case-insensitively
+fn ignore_case(query: &str, smart_case: bool) -> bool {
+  !(smart_case && query.chars().any(char::is_uppercase))
+}
+
+fn is_path_sep(c: char) -> bool {
+  c == '/' || c == '\\'
+}
+
+/// Byte offset of the path relative to the base path in the target,
+/// after the prefix and the following path separators
+fn path_start(target: &str, prefix_len: usize) -> usize {
+  if prefix_len == 0 {
+    return 0;
+  }
+  let rest = &target[prefix_len..];
+  target.len() - rest.trim_start_matches(is_path_sep).len()
+}
+
+/// Cost of a match, by unmatched characters
+fn unmatched_cost(target: &str, matched: usize) -> u32 {
+  (target.chars().count() - matched) as u32
+}
+
+/// Positions of characters in the byte range of the target
+fn range_positions(target: &str, range: std::ops::Range<usize>) -> Vec<bool> {
+  target
+    .char_indices()
+    .map(|(i, _)| range.contains(&i))
+    .collect()
+}
+
+/// Matcher of substrings, or prefixes of the text and path components
+#[derive(Clone)]
+pub struct Substring {
+  prefix: bool,
+  smart_case: bool,
+  ignore_case: bool,
+  query: Vec<char>,
+  target: Vec<char>,
+}
+
+impl Substring {
+  pub fn new(prefix: bool, smart_case: bool) -> Self {
+    Self {
+      prefix,
+      smart_case,
+      ignore_case: true,
+      query: vec![],
+      target: vec![],
+    }
+  }
+
+  /// Index of the first match in characters, after the prefix
+  fn find(&mut self, target: &str, prefix_len: usize) -> Option<usize> {
+    let ignore_case = self.ignore_case;
+    let start = target[..path_start(target, prefix_len)].chars().count();
+    self.target.clear();
+    self.target.extend(target.chars().map(|c| {
+      if ignore_case {
+        fuzzy::fold(c)
+      } else {
+        c
+      }
+    }));
+    let (q, t) = (&self.query, &self.target);
+    if start + q.len() > t.len() {
+      return None;
+    }
+    (start..=t.len() - q.len()).find(|&i| {
+      (!self.prefix || i == start || is_path_sep(t[i - 1]))
+        && t[i..i + q.len()] == q[..]
+    })
+  }
+}
+
+impl Matcher for Substring {
+  fn update_query(&mut self, query: &[char]) {
+    let query_str: String = query.iter().collect();
+    self.ignore_case = ignore_case(&query_str, self.smart_case);
+    self.query.clear();
+    let ignore_case = self.ignore_case;
+    self.query.extend(query.iter().map(|c| {
+      if ignore_case {
+        fuzzy::fold(*c)
+      } else {
+        *c
+      }
+    }));
+  }
+
+  fn narrows_on(&self, _query: &[char], _appended: char) -> bool {
+    // Smart-case may only make the query case-sensitive, which is stricter
+    true
+  }
+
+  fn run(&mut self, target: &str, prefix_len: usize) -> Option<u32> {
+    self.find(target, prefix_len)?;
+    Some(unmatched_cost(target, self.query.len()))
+  }
+
+  fn match_positions(&mut self, target: &str, prefix_len: usize) -> Vec<bool> {
+    let len = self.query.len();
+    let start = self.find(target, prefix_len).unwrap_or(usize::MAX);
+    (0..target.chars().count())
+      .map(|i| i >= start && i < start + len)
+      .collect()
+  }
+
+  fn box_clone(&self) -> Box<dyn Matcher> {
+    Box::new(self.clone())
+  }
+}
+
+/// Matcher of regular expressions.
+/// Invalid expressions, such as ones being typed, match nothing.
+#[derive(Clone)]
+pub struct RegexMatcher {
+  smart_case: bool,
+  regex: Option<Regex>,
+  error: Option<String>,
+}
+
+impl RegexMatcher {
+  pub fn new(smart_case: bool) -> Self {
+    Self {
+      smart_case,
+      regex: None,
+      error: None,
+    }
+  }
+}
+
+impl Matcher for RegexMatcher {
+  fn update_query(&mut self, query: &[char]) {
+    let pattern: String = query.iter().collect();
+    let regex = RegexBuilder::new(&pattern)
+      .case_insensitive(ignore_case(&pattern, self.smart_case))
+      .build();
+    self.error = regex
+      .as_ref()
+      .err()
+      .map(|e| format!("Invalid regex: {}", e));
+    self.regex = regex.ok();
+  }
+
+  fn run(&mut self, target: &str, prefix_len: usize) -> Option<u32> {
+    let path = &target[path_start(target, prefix_len)..];
+    let m = self.regex.as_ref()?.find(path)?;
+    Some(unmatched_cost(target, m.as_str().chars().count()))
+  }
+
+  fn match_positions(&mut self, target: &str, prefix_len: usize) -> Vec<bool> {
+    let start = path_start(target, prefix_len);
+    let path = &target[start..];
+    let range = match self.regex.as_ref().and_then(|r| r.find(path)) {
+      Some(m) => start + m.start()..start + m.end(),
+      None => 0..0,
+    };
+    range_positions(target, range)
+  }
+
+  fn error(&self) -> Option<&str> {
+    self.error.as_deref()
+  }
+
+  fn box_clone(&self) -> Box<dyn Matcher> {
+    Box::new(self.clone())
+  }
+}
+
+/// Matcher of globs, which match the whole text,
+/// or the basename if the glob has no path separator.
+/// Invalid globs match nothing, and the empty glob matches everything.
+#[derive(Clone)]
+pub struct GlobMatcher {
+  smart_case: bool,
+  empty: bool,
+  basename: bool,
+  glob: Option<globset::GlobMatcher>,
+  error: Option<String>,
+}
+
+impl GlobMatcher {
+  pub fn new(smart_case: bool) -> Self {
+    Self {
+      smart_case,
+      empty: true,
+      basename: true,
+      glob: None,
+      error: None,
+    }
+  }
+
+  /// Byte range of the path or its basename to match in the target
+  fn range(&self, target: &str, prefix_len: usize) -> std::ops::Range<usize> {
+    let start = path_start(target, prefix_len);
+    if !self.basename {
+      return start..target.len();
+    }
+    let end = target.trim_end_matches(is_path_sep).len().max(start);
+    let base_start = target[start..end].rfind(is_path_sep).map_or(0, |i| i + 1);
+    start + base_start..end
+  }
+}
+
+impl Matcher for GlobMatcher {
+  fn update_query(&mut self, query: &[char]) {
+    let pattern: String = query.iter().collect();
+    self.empty = pattern.is_empty();
+    self.basename = !pattern.contains(is_path_sep);
+    let glob = GlobBuilder::new(&pattern)
+      .case_insensitive(ignore_case(&pattern, self.smart_case))
+      .build();
+    self.error = glob.as_ref().err().map(|e| format!("Invalid glob: {}", e));
+    self.glob = glob.ok().map(|g| g.compile_matcher());
+  }
+
+  fn run(&mut self, target: &str, prefix_len: usize) -> Option<u32> {
+    if self.empty {
+      return Some(unmatched_cost(target, 0));
+    }
+    let range = self.range(target, prefix_len);
+    let matched = &target[range];
+    if !self.glob.as_ref()?.is_match(matched) {
+      return None;
+    }
+    Some(unmatched_cost(target, matched.chars().count()))
+  }
+
+  fn match_positions(&mut self, target: &str, prefix_len: usize) -> Vec<bool> {
+    if self.empty || self.run(target, prefix_len).is_none() {
+      return vec![false; target.chars().count()];
+    }
+    range_positions(target, self.range(target, prefix_len))
+  }
+
+  fn error(&self) -> Option<&str> {
+    self.error.as_deref()
+  }
+
+  fn box_clone(&self) -> Box<dyn Matcher> {
+    Box::new(self.clone())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ui_finder::Item;
+
+  fn matches(mode: Mode, query: &str, targets: &[&str]) -> Vec<bool> {
+    let mut matcher = mode.matcher(&EditDist::new());
+    matcher.update_query(&query.chars().collect::<Vec<_>>());
+    targets
+      .iter()
+      .map(|t| matcher.run(t, 0).is_some())
+      .collect()
+  }
+
+  #[test]
+  fn modes_match_differently() {
+    let targets = ["work/foo-api", "work/foo-api/src", "src/Main.rs"];
+    assert_eq!(matches(Mode::Fuzzy, "wfa", &targets), [true, true, false]);
+    assert_eq!(matches(Mode::Exact, "o-a", &targets), [true, true, false]);
+    assert_eq!(matches(Mode::Prefix, "foo", &targets), [true, true, false]);
+    assert_eq!(matches(Mode::Prefix, "oo", &targets), [false, false, false]);
+    assert_eq!(
+      matches(Mode::Regex, "^work/.*-api$", &targets),
+      [true, false, false]
+    );
+    assert_eq!(matches(Mode::Regex, "(", &targets), [false, false, false]);
+    assert_eq!(matches(Mode::Glob, "*.rs", &targets), [false, false, true]);
+    assert_eq!(matches(Mode::Glob, "*-api", &targets), [true, false, false]);
+    assert_eq!(matches(Mode::Glob, "work/*", &targets), [true, true, false]);
+    assert_eq!(matches(Mode::Exact, "main", &targets), [false, false, true]);
+  }
+
+  #[test]
+  fn smart_case_applies_to_modes() {
+    let mut fuzzy = EditDist::new();
+    fuzzy.smart_case(true);
+    for mode in [Mode::Exact, Mode::Regex, Mode::Glob] {
+      let mut matcher = mode.matcher(&fuzzy);
+      matcher.update_query(&['M', 'a', 'i', 'n', '*']);
+      assert!(matcher.run("Main*", 0).is_some(), "{}", mode);
+      assert!(matcher.run("main*", 0).is_none(), "{}", mode);
+    }
+  }
+
+  #[test]
+  fn invalid_patterns_are_reported() {
+    for (mode, query) in [(Mode::Regex, "(a"), (Mode::Glob, "[a")] {
+      let mut matcher = mode.matcher(&EditDist::new());
+      matcher.update_query(&query.chars().collect::<Vec<_>>());
+      let error = matcher.error().unwrap();
+      assert!(
+        error.starts_with(&format!("Invalid {}: ", mode)),
+        "{}",
+        error
+      );
+      assert!(matcher.run("(a", 0).is_none());
+
+      matcher.update_query(&['a']);
+      assert_eq!(matcher.error(), None);
+    }
+    let mut matcher = Mode::Exact.matcher(&EditDist::new());
+    matcher.update_query(&['(', 'a']);
+    assert_eq!(matcher.error(), None);
+  }
+
+  #[test]
+  fn highlights_matched_ranges() {
+    let mut matcher = Mode::Regex.matcher(&EditDist::new());
+    matcher.update_query(&['b', '+']);
+    assert_eq!(
+      matcher.match_positions("abbc", 0),
+      [false, true, true, false]
+    );
+    let mut matcher = Mode::Glob.matcher(&EditDist::new());
+    matcher.update_query(&['*', 'c']);
+    assert_eq!(
+      matcher.match_positions("a/bc", 0),
+      [false, false, true, true]
+    );
+  }
+
+  #[test]
+  fn matches_paths_relative_to_base_paths() {
+    let dir = std::env::temp_dir()
+      .join(format!("j2-matcher-test-{}", std::process::id()));
+    for sub in ["work/foo-api/src", "work/bar"] {
+      std::fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    let base = dir.to_str().unwrap().to_string();
+    let items = crate::walker::Walker::new(vec![base]).walk();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let found = |mode: Mode, query: &str| {
+      let mut matcher = mode.matcher(&EditDist::new());
+      matcher.update_query(&query.chars().collect::<Vec<_>>());
+      let mut paths: Vec<&str> = items
+        .iter()
+        .filter(|item| {
+          matcher.run(&item.displayed, item.prefix_len()).is_some()
+        })
+        .map(|item| &item.displayed[item.prefix_len()..])
+        .collect();
+      paths.sort();
+      paths
+    };
+    assert_eq!(found(Mode::Regex, "^work/.*-api$"), ["/work/foo-api"]);
+    assert_eq!(
+      found(Mode::Glob, "work/*"),
+      ["/work/bar", "/work/foo-api", "/work/foo-api/src"]
+    );
+    assert_eq!(found(Mode::Glob, "src"), ["/work/foo-api/src"]);
+    // Base names are not matched, but the paths under them
+    let test = "j2-matcher-test";
+    assert!(found(Mode::Exact, test).is_empty());
+    assert!(found(Mode::Prefix, test).is_empty());
+    assert_eq!(found(Mode::Exact, "api/"), ["/work/foo-api/src"]);
+    assert_eq!(found(Mode::Prefix, "work").len(), 4);
+    assert_eq!(found(Mode::Prefix, "bar"), ["/work/bar"]);
+    assert!(found(Mode::Prefix, "api").is_empty());
+
+    // Highlights are positions in the displayed text
+    let item = items
+      .iter()
+      .find(|i| i.displayed.ends_with("-api"))
+      .unwrap();
+    let mut matcher = Mode::Regex.matcher(&EditDist::new());
+    matcher.update_query(&['f', 'o', 'o']);
+    let matched = matcher.match_positions(&item.displayed, item.prefix_len());
+    let marked: String = item
+      .displayed
+      .chars()
+      .zip(matched)
+      .filter_map(|(c, m)| m.then_some(c))
+      .collect();
+    assert_eq!(marked, "foo");
+    let mut matcher = Mode::Prefix.matcher(&EditDist::new());
+    matcher.update_query(&['w', 'o']);
+    let matched = matcher.match_positions(&item.displayed, item.prefix_len());
+    let start = item.displayed.find("work").unwrap();
+    let expected: Vec<bool> = (0..item.displayed.len())
+      .map(|i| i == start || i == start + 1)
+      .collect();
+    assert_eq!(matched, expected);
+  }
+
+  #[test]
+  fn parse_and_cycle_modes() {
+    assert_eq!("regex".parse::<Mode>(), Ok(Mode::Regex));
+    assert!("fuzzzy".parse::<Mode>().is_err());
+    assert_eq!(Mode::Glob.next(), Mode::Fuzzy);
+  }
+}
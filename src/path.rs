@@ -2,6 +2,8 @@
 
 use std::collections::HashSet;
 
+use crate::fuzzy;
+
 #[derive(Clone)]
 pub struct PathItem {
   // Displayed path (also used for query)
@@ -9,17 +11,41 @@ pub struct PathItem {
 
   // Absolute path (real paths)
   pub abs: String,
+
+  // Name of the base path which the path is found in
+  pub base: Option<String>,
+
+  // Mask of characters of the displayed path, by fuzzy::char_mask
+  mask: u64,
+}
+
+impl PathItem {
+  pub fn new(displayed: String, abs: String, base: Option<String>) -> Self {
+    let mask = fuzzy::char_mask(&displayed);
+    Self {
+      displayed,
+      abs,
+      base,
+      mask,
+    }
+  }
+
+  /// Mask of characters of the displayed path, to skip paths which
+  /// cannot match the query
+  pub fn mask(&self) -> u64 {
+    self.mask
+  }
 }
 
 pub fn convert_base_paths_to_names(base_paths: &Vec<String>) -> Vec<PathItem> {
   let mut set = HashSet::new();
   let mut paths = Vec::new();
   for base_path in base_paths {
+    // Roots such as `/` have no file name
     let last_name = std::path::Path::new(base_path)
       .file_name()
-      .unwrap()
-      .to_str()
-      .unwrap();
+      .and_then(|name| name.to_str())
+      .unwrap_or(base_path);
     let mut name = last_name.to_string();
     let mut c = 0;
 
@@ -29,11 +55,23 @@ pub fn convert_base_paths_to_names(base_paths: &Vec<String>) -> Vec<PathItem> {
     }
     set.insert(name.clone());
 
-    paths.push(PathItem {
-      displayed: name,
-      abs: base_path.clone(),
-    });
+    paths.push(PathItem::new(name, base_path.clone(), None));
   }
 
   paths
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn base_names_fall_back_to_roots() {
+    let base_paths = ["/", "/a/src", "/b/src"].map(String::from).to_vec();
+    let names: Vec<String> = convert_base_paths_to_names(&base_paths)
+      .into_iter()
+      .map(|p| p.displayed)
+      .collect();
+    assert_eq!(names, ["/", "src", "src-0"]);
+  }
+}
@@ -0,0 +1,354 @@
+//! Scoring of items for the finder, in worker threads for large lists.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fuzzy;
+use crate::matcher::Matcher;
+
+/// Lists shorter than this are scored in the calling thread
+const PARALLEL_MIN: usize = 4096;
+
+/// Number of items scored by a worker at once
+const CHUNK_SIZE: usize = 2048;
+
+/// Text to score, with its mask of characters by `fuzzy::char_mask`
+pub trait Scored: AsRef<str> {
+  fn mask(&self) -> u64;
+
+  /// Length of the prefix before the path, by `ui_finder::Item::prefix_len`
+  fn prefix_len(&self) -> usize {
+    0
+  }
+}
+
+impl Scored for String {
+  fn mask(&self) -> u64 {
+    fuzzy::char_mask(self)
+  }
+}
+
+/// Score the text without typos, skipping texts lacking query characters
+fn run_strict<S: Scored>(ed: &mut dyn Matcher, text: &S) -> Option<u32> {
+  if ed.may_match(text.mask()) {
+    ed.run(text.as_ref(), text.prefix_len())
+  } else {
+    None
+  }
+}
+
+fn run_tolerant<S: Scored>(ed: &mut dyn Matcher, text: &S) -> Option<u32> {
+  ed.run_tolerant(text.as_ref(), text.prefix_len())
+}
+
+/// Scored chunk, with matches of (cost, index)
+struct Batch {
+  scored: usize,
+  matches: Vec<(u32, usize)>,
+}
+
+/// Scoring in worker threads.
+/// Dropping the job cancels it, because workers stop when they see the
+/// cancel flag or fail to send.
+struct Job {
+  cancel: Arc<AtomicBool>,
+  receiver: Receiver<Batch>,
+}
+
+impl Drop for Job {
+  fn drop(&mut self) {
+    self.cancel.store(true, Ordering::Relaxed);
+  }
+}
+
+/// Scorer of texts for the current query
+pub struct Scorer<S> {
+  texts: Arc<Vec<S>>,
+  threads: usize,
+  // Indices of texts to score, or None for all
+  candidates: Option<Arc<Vec<usize>>>,
+  remaining: usize,
+  // Parallel job, or None to score in the calling thread
+  job: Option<Job>,
+  // Matcher, which is cloned into workers
+  ed: Box<dyn Matcher>,
+  // Whether typos are allowed
+  tolerant: bool,
+  // Sequential status
+  next: usize,
+}
+
+impl<S: Scored + Send + Sync + 'static> Scorer<S> {
+  pub fn new(texts: Arc<Vec<S>>, ed: Box<dyn Matcher>) -> Self {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    Self {
+      texts,
+      threads,
+      candidates: None,
+      remaining: 0,
+      job: None,
+      ed,
+      tolerant: false,
+      next: 0,
+    }
+  }
+
+  pub fn texts(&self) -> &Arc<Vec<S>> {
+    &self.texts
+  }
+
+  /// Number of items not scored yet
+  pub fn remaining(&self) -> usize {
+    self.remaining
+  }
+
+  /// Whether the current scoring allows typos
+  pub fn is_tolerant(&self) -> bool {
+    self.tolerant
+  }
+
+  /// Cancel the current scoring, and replace the matcher,
+  /// such as for another mode, keeping the texts to score
+  pub fn set_matcher(&mut self, ed: Box<dyn Matcher>) {
+    self.job = None;
+    self.remaining = 0;
+    self.ed = ed;
+  }
+
+  /// Cancel the current scoring, and start scoring for the query
+  pub fn start(&mut self, query: &[char]) {
+    self.tolerant = false;
+    self.start_with(query, None);
+  }
+
+  /// Start scoring for the query, allowing typos.
+  /// Used when nothing matches the query without typos.
+  pub fn start_tolerant(&mut self, query: &[char]) {
+    self.tolerant = true;
+    self.start_with(query, None);
+  }
+
+  /// Start scoring only the candidates, such as matches of the query
+  /// before a character is appended.
+  /// Typos are allowed if they were for the candidates.
+  pub fn start_within(&mut self, query: &[char], candidates: Vec<usize>) {
+    self.start_with(query, Some(Arc::new(candidates)));
+  }
+
+  fn start_with(
+    &mut self,
+    query: &[char],
+    candidates: Option<Arc<Vec<usize>>>,
+  ) {
+    self.job = None;
+    self.candidates = candidates;
+    self.remaining = self.len();
+    self.ed.update_query(query);
+    self.next = 0;
+    if self.len() >= PARALLEL_MIN && self.threads > 1 {
+      self.job = Some(self.spawn());
+    }
+  }
+
+  /// Number of items to score
+  fn len(&self) -> usize {
+    self
+      .candidates
+      .as_ref()
+      .map_or(self.texts.len(), |c| c.len())
+  }
+
+  fn run_fn(&self) -> fn(&mut dyn Matcher, &S) -> Option<u32> {
+    if self.tolerant {
+      run_tolerant
+    } else {
+      run_strict
+    }
+  }
+
+  fn spawn(&self) -> Job {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let next = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..self.threads {
+      let (cancel, next) = (cancel.clone(), next.clone());
+      let sender = sender.clone();
+      let texts = self.texts.clone();
+      let candidates = self.candidates.clone();
+      let len = self.len();
+      let mut ed = self.ed.clone();
+      let run = self.run_fn();
+      thread::spawn(move || {
+        while !cancel.load(Ordering::Relaxed) {
+          let start = next.fetch_add(CHUNK_SIZE, Ordering::Relaxed);
+          if start >= len {
+            break;
+          }
+          let end = (start + CHUNK_SIZE).min(len);
+          let matches = (start..end)
+            .map(|i| candidates.as_ref().map_or(i, |c| c[i]))
+            .filter_map(|i| run(ed.as_mut(), &texts[i]).map(|cost| (cost, i)))
+            .collect();
+          let batch = Batch {
+            scored: end - start,
+            matches,
+          };
+          if sender.send(batch).is_err() {
+            break;
+          }
+        }
+      });
+    }
+    Job { cancel, receiver }
+  }
+
+  /// Score items for the duration, and pass matches of (cost, index).
+  /// Return true if some items are scored.
+  pub fn poll(
+    &mut self,
+    duration: Duration,
+    mut on_match: impl FnMut(u32, usize),
+  ) -> bool {
+    let now = Instant::now();
+    let before = self.remaining;
+    match &self.job {
+      None => {
+        // Score from the end, as the finder has done
+        let len = self.len();
+        let run = self.run_fn();
+        while now.elapsed() < duration && self.next < len {
+          let i = len - 1 - self.next;
+          let idx = self.candidates.as_ref().map_or(i, |c| c[i]);
+          if let Some(cost) = run(self.ed.as_mut(), &self.texts[idx]) {
+            on_match(cost, idx);
+          }
+          self.next += 1;
+          self.remaining -= 1;
+        }
+      }
+      Some(job) => {
+        while self.remaining > 0 {
+          let timeout = duration.saturating_sub(now.elapsed());
+          let batch = match job.receiver.recv_timeout(timeout) {
+            Ok(batch) => batch,
+            Err(RecvTimeoutError::Timeout) => break,
+            Err(RecvTimeoutError::Disconnected) => break,
+          };
+          for (cost, idx) in batch.matches {
+            on_match(cost, idx);
+          }
+          self.remaining -= batch.scored;
+        }
+      }
+    }
+    self.remaining != before
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn score_all(texts: Vec<String>, query: &str) -> Vec<(u32, usize)> {
+    let mut scorer =
+      Scorer::new(Arc::new(texts), Box::new(fuzzy::EditDist::new()));
+    scorer.start(&query.chars().collect::<Vec<_>>());
+    let mut matches = vec![];
+    while scorer.remaining() > 0 {
+      scorer.poll(Duration::from_millis(100), |cost, idx| {
+        matches.push((cost, idx))
+      });
+    }
+    matches.sort();
+    matches
+  }
+
+  #[test]
+  fn parallel_scores_equal_to_sequential() {
+    let texts: Vec<String> = (0..PARALLEL_MIN * 3 + 7)
+      .map(|i| format!("src: /dir{}/sub{}/file{}", i % 97, i % 13, i))
+      .collect();
+    let sequential: Vec<_> = {
+      let mut ed = fuzzy::EditDist::new();
+      ed.update_query(&"d1s3".chars().collect::<Vec<_>>());
+      let mut m: Vec<_> = (0..texts.len())
+        .filter_map(|i| ed.run(&texts[i]).map(|cost| (cost, i)))
+        .collect();
+      m.sort();
+      m
+    };
+    assert!(!sequential.is_empty());
+    assert_eq!(score_all(texts, "d1s3"), sequential);
+  }
+
+  #[test]
+  fn start_within_scores_only_candidates() {
+    for len in [10, PARALLEL_MIN * 2] {
+      let texts: Vec<String> = (0..len).map(|i| format!("item{}", i)).collect();
+      let mut scorer =
+        Scorer::new(Arc::new(texts), Box::new(fuzzy::EditDist::new()));
+      let candidates: Vec<usize> = (0..len).filter(|i| i % 2 == 0).collect();
+      scorer.start_within(&['i', '1'], candidates);
+      assert_eq!(scorer.remaining(), len.div_ceil(2));
+      let mut matches = vec![];
+      while scorer.remaining() > 0 {
+        scorer.poll(Duration::from_millis(100), |_, i| matches.push(i));
+      }
+      matches.sort();
+      let expected: Vec<usize> = (0..len)
+        .filter(|i| i % 2 == 0 && i.to_string().contains('1'))
+        .collect();
+      assert_eq!(matches, expected);
+    }
+  }
+
+  #[test]
+  fn set_matcher_keeps_texts() {
+    let texts: Vec<String> = (0..PARALLEL_MIN * 2)
+      .map(|i| format!("item{}", i))
+      .collect();
+    let mut scorer =
+      Scorer::new(Arc::new(texts), Box::new(fuzzy::EditDist::new()));
+    let texts = scorer.texts().clone();
+    scorer.start(&['1', '2']);
+    let mut exact =
+      crate::matcher::Mode::Exact.matcher(&fuzzy::EditDist::new());
+    exact.update_query(&['1', '2']);
+    scorer.set_matcher(exact);
+    assert_eq!(scorer.remaining(), 0);
+    assert!(Arc::ptr_eq(scorer.texts(), &texts));
+
+    scorer.start(&['1', '2']);
+    let mut matches = 0;
+    while scorer.remaining() > 0 {
+      scorer.poll(Duration::from_millis(100), |_, _| matches += 1);
+    }
+    let expected = (0..PARALLEL_MIN * 2)
+      .filter(|i| i.to_string().contains("12"))
+      .count();
+    assert_eq!(matches, expected);
+  }
+
+  #[test]
+  fn restart_cancels_previous_job() {
+    let texts: Vec<String> = (0..PARALLEL_MIN * 4)
+      .map(|i| format!("item{}", i))
+      .collect();
+    let mut scorer =
+      Scorer::new(Arc::new(texts), Box::new(fuzzy::EditDist::new()));
+    scorer.start(&['i']);
+    scorer.start(&['9', '9', '9']);
+    let mut matches = 0;
+    while scorer.remaining() > 0 {
+      scorer.poll(Duration::from_millis(100), |_, _| matches += 1);
+    }
+    // Items with three 9s, as the query is a subsequence
+    let expected = (0..PARALLEL_MIN * 4)
+      .filter(|i| i.to_string().matches('9').count() >= 3)
+      .count();
+    assert_eq!(matches, expected);
+  }
+}
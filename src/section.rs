@@ -1,5 +1,5 @@
 use chrono::{Datelike, Timelike};
-use std::time;
+use std::{fmt, time};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub enum JoneSectionBase {
@@ -12,7 +12,8 @@ pub struct JoneSection {
   pub year: u32,
   pub month: u32,
   pub day: u32,
-  pub sys_time: time::SystemTime,
+  /// Creation time, or None if the file system does not record it
+  pub sys_time: Option<time::SystemTime>,
   pub rand: u32,
   pub base: JoneSectionBase,
 }
@@ -27,7 +28,7 @@ impl JoneSection {
     let non_rand = now.second() + 60 * (now.minute() + 60 * now.hour());
     let rand = non_rand * 10 + (rand::random::<u32>() % 10);
     Self {
-      sys_time: time::SystemTime::now(),
+      sys_time: Some(time::SystemTime::now()),
       year: now.year_ce().1 % 100,
       month: now.month(),
       day: now.day(),
@@ -66,31 +67,24 @@ impl JoneSection {
     )
   }
 
-  pub fn to_string(&self) -> String {
-    match self.base {
-      JoneSectionBase::Base36 => self.to_base36(),
-      JoneSectionBase::Base10 => self.to_base10(),
-    }
-  }
-
   pub fn from_str(s: &str, sys_time: Option<time::SystemTime>) -> Option<Self> {
     if s.len() == 9 && s[4..5].contains('-') {
       // Base36
       Some(Self {
-        year: u32::from_str_radix(&s[0..2], 10).ok()?,
+        year: s[0..2].parse().ok()?,
         month: u32::from_str_radix(&s[2..3], 36).ok()?,
         day: u32::from_str_radix(&s[3..4], 36).ok()?,
-        sys_time: sys_time.unwrap_or(time::SystemTime::now()),
+        sys_time,
         rand: u32::from_str_radix(&s[5..9], 36).ok()?,
         base: JoneSectionBase::Base36,
       })
     } else if s.len() == 11 && s[6..7].contains("-") {
       // Base10
       Some(Self {
-        year: u32::from_str_radix(&s[0..2], 10).ok()?,
-        month: u32::from_str_radix(&s[2..4], 10).ok()?,
-        day: u32::from_str_radix(&s[4..6], 10).ok()?,
-        sys_time: sys_time.unwrap_or(time::SystemTime::now()),
+        year: s[0..2].parse().ok()?,
+        month: s[2..4].parse().ok()?,
+        day: s[4..6].parse().ok()?,
+        sys_time,
         rand: u32::from_str_radix(&s[7..11], 36).ok()?,
         base: JoneSectionBase::Base10,
       })
@@ -99,3 +93,12 @@ impl JoneSection {
     }
   }
 }
+
+impl fmt::Display for JoneSection {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.base {
+      JoneSectionBase::Base36 => f.write_str(&self.to_base36()),
+      JoneSectionBase::Base10 => f.write_str(&self.to_base10()),
+    }
+  }
+}
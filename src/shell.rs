@@ -1,5 +1,6 @@
 pub enum ShellType {
   Sh,
+  Fish,
   Pwsh,
 }
 
@@ -7,6 +8,7 @@ impl ShellType {
   pub fn to_str(&self) -> &str {
     match self {
       ShellType::Sh => "sh",
+      ShellType::Fish => "fish",
       ShellType::Pwsh => "pwsh",
     }
   }
@@ -16,7 +18,7 @@ impl ShellType {
       "sh" => Some(ShellType::Sh),
       "bash" => Some(ShellType::Sh),
       "zsh" => Some(ShellType::Sh),
-      "fish" => Some(ShellType::Sh),
+      "fish" => Some(ShellType::Fish),
       "pwsh" => Some(ShellType::Pwsh),
       "powershell" => Some(ShellType::Pwsh),
       _ => None,
@@ -0,0 +1,163 @@
+//! Color themes of the finder.
+//!
+//! A theme spec is a comma-separated list of preset names
+//! (`dark`, `light`, `no-color`) and `<ELEMENT>:<COLOR>[:<MODIFIER>...]`,
+//! such as `light,prompt:blue:bold,selected:#ff8800`.
+//! Colors are names (`red`, `light-red`, `gray`, ...), `#rrggbb`,
+//! 256-color indices or `default`.
+//! Modifiers are `bold`, `dim`, `italic`, `underline` and `reverse`.
+
+use std::str::FromStr;
+
+use ratatui::style::{Color, Modifier, Style, Stylize};
+
+/// Styles of the finder
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+  /// Prompt before the query
+  pub prompt: Style,
+  /// Query in the input line
+  pub query: Style,
+  /// Items in the list
+  pub text: Style,
+  /// Selected item
+  pub selected: Style,
+  /// Characters of items matched to the query
+  pub matched: Style,
+  /// Base path name prefixed to paths
+  pub base: Style,
+  /// Counters and filters in the border
+  pub counter: Style,
+  /// Border between the list and the input line
+  pub border: Style,
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Self::dark()
+  }
+}
+
+impl Theme {
+  /// Theme for dark backgrounds
+  pub fn dark() -> Self {
+    Self {
+      prompt: Style::new().fg(Color::LightBlue).bold(),
+      query: Style::new(),
+      text: Style::new(),
+      selected: Style::new().fg(Color::LightRed).bold(),
+      matched: Style::new().fg(Color::LightGreen),
+      base: Style::new().fg(Color::DarkGray),
+      counter: Style::new().fg(Color::Yellow),
+      border: Style::new().fg(Color::DarkGray),
+    }
+  }
+
+  /// Theme for light backgrounds
+  pub fn light() -> Self {
+    Self {
+      prompt: Style::new().fg(Color::Blue).bold(),
+      query: Style::new(),
+      text: Style::new(),
+      selected: Style::new().fg(Color::Red).bold(),
+      matched: Style::new().fg(Color::Green),
+      base: Style::new().fg(Color::Gray),
+      counter: Style::new().fg(Color::Magenta),
+      border: Style::new().fg(Color::Gray),
+    }
+  }
+
+  /// Theme without colors, only with modifiers
+  pub fn no_color() -> Self {
+    Self {
+      prompt: Style::new().bold(),
+      query: Style::new(),
+      text: Style::new(),
+      selected: Style::new().bold(),
+      matched: Style::new().underlined(),
+      base: Style::new().dim(),
+      counter: Style::new(),
+      border: Style::new(),
+    }
+  }
+
+  fn element_mut(&mut self, name: &str) -> Option<&mut Style> {
+    Some(match name {
+      "prompt" => &mut self.prompt,
+      "query" => &mut self.query,
+      "text" => &mut self.text,
+      "selected" => &mut self.selected,
+      "match" | "matched" => &mut self.matched,
+      "base" => &mut self.base,
+      "counter" => &mut self.counter,
+      "border" => &mut self.border,
+      _ => return None,
+    })
+  }
+
+  /// Apply a spec such as `light,prompt:blue:bold`
+  pub fn apply_spec(&mut self, spec: &str) -> Result<(), String> {
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+      let mut parts = item.split(':');
+      let name = parts.next().unwrap_or_default();
+      let preset = match name {
+        "dark" => Some(Self::dark()),
+        "light" => Some(Self::light()),
+        "no-color" | "bw" => Some(Self::no_color()),
+        _ => None,
+      };
+      if let Some(preset) = preset {
+        *self = preset;
+        continue;
+      }
+
+      let style = self
+        .element_mut(name)
+        .ok_or_else(|| format!("Invalid element '{}'", name))?;
+      let mut new_style = Style::new();
+      for part in parts {
+        new_style = match part {
+          "bold" => new_style.bold(),
+          "dim" => new_style.dim(),
+          "italic" => new_style.italic(),
+          "underline" => new_style.underlined(),
+          "reverse" => new_style.add_modifier(Modifier::REVERSED),
+          "default" => new_style.fg(Color::Reset),
+          color => new_style.fg(
+            Color::from_str(color)
+              .map_err(|_| format!("Invalid color '{}'", color))?,
+          ),
+        };
+      }
+      if new_style == Style::new() {
+        return Err(format!("Invalid color spec '{}'", item));
+      }
+      *style = new_style;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn apply_spec_overrides_presets() {
+    let mut theme = Theme::default();
+    theme
+      .apply_spec("light, prompt:blue:bold ,match:#ff8800,base:244")
+      .unwrap();
+    assert_eq!(theme.selected, Theme::light().selected);
+    assert_eq!(theme.prompt, Style::new().fg(Color::Blue).bold());
+    assert_eq!(theme.matched, Style::new().fg(Color::Rgb(255, 136, 0)));
+    assert_eq!(theme.base, Style::new().fg(Color::Indexed(244)));
+
+    theme.apply_spec("no-color").unwrap();
+    assert_eq!(theme, Theme::no_color());
+
+    assert!(theme.apply_spec("prompt").is_err());
+    assert!(theme.apply_spec("prompt:blurple").is_err());
+    assert!(theme.apply_spec("cursor:red").is_err());
+  }
+}
@@ -1,42 +1,187 @@
+//! Fuzzy finder TUI over any list of items.
+//!
+//! ```no_run
+//! use j2::ui_finder::Finder;
+//!
+//! let items = vec!["foo".to_string(), "bar".to_string()];
+//! if let Some(sel) = Finder::new(items).query("fo").run()? {
+//!   println!("{}", sel.item);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
 use std::collections::BTreeMap;
-use std::io::{self, stderr};
-use std::time::Duration;
+use std::io::{self, stderr, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub use crossterm::event::{Event, KeyCode, KeyModifiers};
 
-use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::cursor::{MoveTo, Show};
+use crossterm::event::{
+  DisableMouseCapture, EnableMouseCapture, KeyEventKind, MouseButton,
+  MouseEvent, MouseEventKind,
+};
 use crossterm::terminal::{
   disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
-use crossterm::{event, ExecutableCommand};
-use ratatui::layout::{Constraint, Layout, Position};
-use ratatui::style::{self, Style};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{event, ExecutableCommand, QueueableCommand};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::text::{Line, Span};
 use ratatui::widgets::{
   Block, Borders, HighlightSpacing, List, ListDirection, ListState, Paragraph,
+  Scrollbar, ScrollbarOrientation, ScrollbarState,
 };
-use ratatui::Frame;
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{Frame, Terminal, TerminalOptions, Viewport};
 use unicode_width::UnicodeWidthChar;
 
 use crate::fuzzy;
-use crate::path::{self, PathItem};
+use crate::history::History;
+use crate::keymap::{Action, Keymap};
+use crate::matcher::{Matcher, Mode};
+use crate::path::PathItem;
+use crate::scorer::{Scored, Scorer};
+use crate::theme::Theme;
+
+/// An item which can be found by the finder
+pub trait Item {
+  /// Text to display, which is also used for matching
+  fn displayed(&self) -> &str;
+
+  /// Length of the prefix of the displayed text, rendered dimmer
+  fn prefix_len(&self) -> usize {
+    0
+  }
+
+  /// Mask of characters of the displayed text by `fuzzy::char_mask`
+  fn mask(&self) -> u64 {
+    fuzzy::char_mask(self.displayed())
+  }
+}
+
+impl Item for PathItem {
+  fn displayed(&self) -> &str {
+    &self.displayed
+  }
+
+  fn mask(&self) -> u64 {
+    PathItem::mask(self)
+  }
+
+  /// Base path name and the following `: `
+  fn prefix_len(&self) -> usize {
+    match &self.base {
+      Some(base)
+        if self.displayed.starts_with(base.as_str())
+          && self.displayed[base.len()..].starts_with(": ") =>
+      {
+        base.len() + 2
+      }
+      _ => 0,
+    }
+  }
+}
+
+impl Item for String {
+  fn displayed(&self) -> &str {
+    self
+  }
+}
+
+/// Filter of a source, toggled inside the finder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Toggle {
+  /// Include (non-directory) files or not
+  Files,
+  /// Include hidden files or not
+  Hidden,
+  /// Restrict to the next base path, or all base paths after the last
+  Base,
+}
+
+/// A source which loads items for the finder
+pub trait Source<T> {
+  fn load(&mut self) -> Vec<T>;
+
+  /// Toggle a filter, and return true if items should be reloaded
+  fn toggle(&mut self, _toggle: Toggle) -> bool {
+    false
+  }
+
+  /// State of filters shown in the finder, or empty if there is none
+  fn status(&self) -> String {
+    String::new()
+  }
+}
+
+impl<T: Clone> Source<T> for Vec<T> {
+  fn load(&mut self) -> Vec<T> {
+    self.clone()
+  }
+}
+
+/// A source of terminal events for the finder
+pub trait EventSource {
+  /// Check if an event is available within the timeout
+  fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+  /// Read an event, blocking until one is available
+  fn read(&mut self) -> io::Result<Event>;
+}
+
+/// Events from the terminal, read by crossterm
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+  fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+    event::poll(timeout)
+  }
+
+  fn read(&mut self) -> io::Result<Event> {
+    event::read()
+  }
+}
+
+/// An item selected in the finder
+pub struct Selection<T> {
+  pub item: T,
+  pub cost: u32,
+  /// Name of the action bound to the accepting key.
+  /// None if accepted by Enter.
+  pub action: Option<String>,
+}
 
 #[derive(Clone, PartialEq, PartialOrd, Eq, Ord)]
 struct FilteredKey {
   cost: u32,
+  // Rank of the item by `fuzzy::tie_key`
+  tie: usize,
   index: usize,
 }
 
-struct State {
+struct State<T> {
+  source: Option<Box<dyn Source<T>>>,
+  status: String,
+
   // Event loop status
   quit: bool,
-  ret: Option<String>,
+  ret: Option<FilteredKey>,
+  ret_action: Option<String>,
   need_to_redraw: bool,
 
+  keymap: Keymap,
+
   // Fuzzy finder status
-  list: Vec<PathItem>,
+  list: Vec<T>,
   query: Vec<char>,
 
-  ed: fuzzy::EditDist,
-  unfiltered_count: usize,
+  // Fuzzy matcher with options, from which matchers of modes are made
+  fuzzy: fuzzy::EditDist,
+  mode: Mode,
+  ed: Box<dyn Matcher>,
+  scorer: Scorer<ListItem>,
   filtered: BTreeMap<FilteredKey, usize>,
 
   // Editing status
@@ -45,46 +190,166 @@ struct State {
   query_string: String,
 
   // List status
-  list_items: Vec<String>,
   list_state: ListState,
+  page_size: usize,
+  reverse: bool,
+
+  // History status.
+  // Position is the length of history while editing a new query,
+  // and the draft is the new query.
+  history: Option<History>,
+  history_pos: usize,
+  history_draft: String,
+
+  theme: Theme,
+
+  // Mouse status
+  list_area: Rect,
+  last_click: Option<(usize, Instant)>,
+}
+
+/// Displayed text of an item, the length of its prefix,
+/// its rank for ties of costs and its mask of characters
+struct ListItem {
+  text: String,
+  prefix_len: usize,
+  tie: usize,
+  mask: u64,
+}
+
+impl AsRef<str> for ListItem {
+  fn as_ref(&self) -> &str {
+    &self.text
+  }
+}
+
+impl Scored for ListItem {
+  fn mask(&self) -> u64 {
+    self.mask
+  }
+
+  fn prefix_len(&self) -> usize {
+    self.prefix_len
+  }
+}
+
+fn list_scorer<T: Item>(list: &[T], ed: &dyn Matcher) -> Scorer<ListItem> {
+  let mut items: Vec<ListItem> = list
+    .iter()
+    .map(|s| ListItem {
+      text: s.displayed().to_string(),
+      prefix_len: s.prefix_len(),
+      tie: 0,
+      mask: s.mask(),
+    })
+    .collect();
+  let mut order: Vec<usize> = (0..items.len()).collect();
+  order.sort_by_cached_key(|&i| fuzzy::tie_key(&items[i].text));
+  for (tie, i) in order.into_iter().enumerate() {
+    items[i].tie = tie;
+  }
+  Scorer::new(Arc::new(items), ed.box_clone())
+}
+
+/// Line of the item, with styles of the prefix and matched characters
+fn item_line<'a>(
+  theme: &Theme,
+  matched: &[bool],
+  text: &'a str,
+  prefix_len: usize,
+) -> Line<'a> {
+  let style_at = |i: usize, pos: usize| {
+    let style = if pos < prefix_len {
+      theme.base
+    } else {
+      theme.text
+    };
+    if matched[i] {
+      style.patch(theme.matched)
+    } else {
+      style
+    }
+  };
+
+  // Split into spans of the same style
+  let mut spans = vec![];
+  let mut start = 0;
+  let mut start_style = None;
+  for (i, (pos, _)) in text.char_indices().enumerate() {
+    let style = style_at(i, pos);
+    if start_style != Some(style) {
+      if let Some(prev) = start_style {
+        spans.push(Span::styled(&text[start..pos], prev));
+      }
+      start = pos;
+      start_style = Some(style);
+    }
+  }
+  if let Some(style) = start_style {
+    spans.push(Span::styled(&text[start..], style));
+  }
+  Line::from(spans)
 }
 
-impl State {
-  fn new(list: Vec<path::PathItem>, init_query: &String) -> Self {
-    let cursor = init_query.len();
-    let list_items = list.iter().map(|s| s.displayed.clone()).collect();
+impl<T: Item> State<T> {
+  fn new(
+    list: Vec<T>,
+    init_query: &str,
+    fuzzy: fuzzy::EditDist,
+    mode: Mode,
+  ) -> Self {
+    let cursor = init_query.chars().count();
     let list_state = ListState::default();
     let query: Vec<char> = init_query.chars().collect();
-    let unfiltered_count = list.len();
+    let mut ed = mode.matcher(&fuzzy);
+    ed.update_query(&query);
+    let mut scorer = list_scorer(&list, ed.as_ref());
+    scorer.start(&query);
     let ui_cursor = query
       .iter()
       .fold(0, |a, c| a + UnicodeWidthChar::width(*c).unwrap_or(0));
-    let mut ed = fuzzy::EditDist::new();
-    ed.update_query(&query);
     Self {
+      source: None,
+      status: String::new(),
+
       quit: false,
       ret: None,
+      ret_action: None,
       need_to_redraw: true,
 
+      keymap: Keymap::default(),
+
       list,
       query,
 
+      fuzzy,
+      mode,
       ed,
-      unfiltered_count,
+      scorer,
       filtered: BTreeMap::new(),
 
       cursor,
       ui_cursor,
-      query_string: init_query.clone(),
+      query_string: init_query.to_string(),
 
-      list_items,
       list_state,
+      page_size: 1,
+      reverse: false,
+
+      history: None,
+      history_pos: 0,
+      history_draft: String::new(),
+
+      theme: Theme::default(),
+
+      list_area: Rect::default(),
+      last_click: None,
     }
   }
 
   fn move_cursor(&mut self, mut offset: isize) {
     while offset < 0 {
-      if self.cursor <= 0 {
+      if self.cursor == 0 {
         break;
       }
       self.cursor -= 1;
@@ -104,16 +369,56 @@ impl State {
     self.need_to_redraw = true;
   }
 
+  fn set_cursor(&mut self, cursor: usize) {
+    self.cursor = cursor.min(self.query.len());
+    self.ui_cursor = self.query[..self.cursor]
+      .iter()
+      .fold(0, |a, c| a + UnicodeWidthChar::width(*c).unwrap_or(0));
+    self.need_to_redraw = true;
+  }
+
+  fn word_start_before(&self, mut pos: usize) -> usize {
+    while pos > 0 && !self.query[pos - 1].is_alphanumeric() {
+      pos -= 1;
+    }
+    while pos > 0 && self.query[pos - 1].is_alphanumeric() {
+      pos -= 1;
+    }
+    pos
+  }
+
+  fn word_end_after(&self, mut pos: usize) -> usize {
+    while pos < self.query.len() && !self.query[pos].is_alphanumeric() {
+      pos += 1;
+    }
+    while pos < self.query.len() && self.query[pos].is_alphanumeric() {
+      pos += 1;
+    }
+    pos
+  }
+
+  fn delete_range(&mut self, start: usize, end: usize) {
+    let end = end.min(self.query.len());
+    if start >= end {
+      return;
+    }
+    self.query.drain(start..end);
+    self.set_cursor(start);
+    self.clear_filtered();
+  }
+
   fn move_selected_item(&mut self, offset: isize) {
-    let new_index = self.list_state.selected().unwrap_or(0) as isize + offset;
-    self.list_state.select(Some(
-      new_index.clamp(0, self.list.len() as isize - 1) as usize,
-    ));
+    let selected = self.list_state.selected().unwrap_or(0) as isize;
+    let new_index = selected.saturating_add(offset);
+    let last = self.filtered.len().saturating_sub(1) as isize;
+    self
+      .list_state
+      .select(Some(new_index.clamp(0, last) as usize));
     self.need_to_redraw = true;
   }
 
   fn backspace(&mut self) {
-    if self.cursor <= 0 {
+    if self.cursor == 0 {
       return;
     }
     self.move_cursor(-1);
@@ -122,92 +427,265 @@ impl State {
   }
 
   fn insert(&mut self, new_char: char) {
+    let appended = self.cursor == self.query.len();
     self.query.insert(self.cursor, new_char);
     self.move_cursor(1);
+    if appended {
+      self.narrow_filtered(new_char);
+    } else {
+      self.clear_filtered();
+    }
+  }
+
+  /// Rescore only the current matches, because the query is extended.
+  /// Fall back to all items if the scoring is not done yet,
+  /// or if the appended character may make new matches.
+  fn narrow_filtered(&mut self, appended: char) {
+    let query = &self.query[..self.query.len() - 1];
+    if self.scorer.remaining() > 0 || !self.ed.narrows_on(query, appended) {
+      return self.clear_filtered();
+    }
+    let mut candidates: Vec<usize> = self.filtered.values().copied().collect();
+    candidates.sort_unstable();
+    self.reset_filtered();
+    self.scorer.start_within(&self.query, candidates);
+  }
+
+  fn set_query(&mut self, query: &str) {
+    self.query = query.chars().collect();
+    self.set_cursor(self.query.len());
+    self.clear_filtered();
+  }
+
+  /// Move to the history entry at the position, or the draft at the end
+  fn move_history(&mut self, pos: usize) {
+    let Some(history) = &self.history else {
+      return;
+    };
+    let entries = history.entries();
+    if pos > entries.len() || pos == self.history_pos {
+      return;
+    }
+    if self.history_pos == entries.len() {
+      self.history_draft = self.query_string.clone();
+    }
+    let query = match entries.get(pos) {
+      Some(entry) => entry.clone(),
+      None => self.history_draft.clone(),
+    };
+    self.history_pos = pos;
+    self.set_query(&query);
+  }
+
+  fn search_history(&mut self) {
+    let Some(history) = &self.history else {
+      return;
+    };
+    let entries = history.entries();
+    let pattern = if self.history_pos == entries.len() {
+      &self.query_string
+    } else {
+      &self.history_draft
+    };
+    let found = entries[..self.history_pos]
+      .iter()
+      .rposition(|e| e.contains(pattern.as_str()) && *e != self.query_string);
+    if let Some(pos) = found {
+      self.move_history(pos);
+    }
+  }
+
+  fn toggle(&mut self, toggle: Toggle) {
+    let Some(source) = self.source.as_mut() else {
+      return;
+    };
+    if source.toggle(toggle) {
+      self.list = source.load();
+      self.status = source.status();
+      self.scorer = list_scorer(&self.list, self.ed.as_ref());
+      self.clear_filtered();
+    }
+  }
+
+  fn cycle_mode(&mut self) {
+    self.mode = self.mode.next();
+    self.ed = self.mode.matcher(&self.fuzzy);
+    self.scorer.set_matcher(self.ed.box_clone());
     self.clear_filtered();
   }
 
   fn clear_filtered(&mut self) {
+    self.reset_filtered();
+    self.scorer.start(&self.query);
+  }
+
+  fn reset_filtered(&mut self) {
     self.query_string = self.query.iter().collect();
     self.ed.update_query(&self.query);
     self.filtered.clear();
-    self.unfiltered_count = self.list.len();
+    self.list_state.select(Some(0));
     self.need_to_redraw = true;
   }
 
   fn filter_slightly(&mut self, duration: Duration) {
-    // Get now to check duration
-    let now = std::time::Instant::now();
-    while now.elapsed() < duration && self.unfiltered_count > 0 {
-      let idx = self.unfiltered_count - 1;
-      let item = &self.list[idx];
-
-      // Calculate cost
-      if let Some(cost) = self.ed.run(&item.displayed) {
-        self.filtered.insert(FilteredKey { cost, index: idx }, idx);
-      }
-
-      self.unfiltered_count -= 1;
+    let filtered = &mut self.filtered;
+    let texts = self.scorer.texts().clone();
+    let scored = self.scorer.poll(duration, |cost, index| {
+      let tie = texts[index].tie;
+      filtered.insert(FilteredKey { cost, tie, index }, index);
+    });
+    if scored {
+      self.need_to_redraw = true;
+    }
+    // Allow typos only if nothing matches
+    if self.scorer.remaining() == 0
+      && self.filtered.is_empty()
+      && !self.scorer.is_tolerant()
+      && !self.query.is_empty()
+      && self.ed.tolerates_typos()
+    {
+      self.scorer.start_tolerant(&self.query);
       self.need_to_redraw = true;
     }
   }
+
+  fn into_selection(mut self) -> Option<Selection<T>> {
+    let key = self.ret?;
+    if let Some(history) = &mut self.history {
+      history.add(&self.query_string);
+      // History is not essential, so ignore errors
+      let _ = history.save();
+    }
+    Some(Selection {
+      item: self.list.swap_remove(key.index),
+      cost: key.cost,
+      action: self.ret_action,
+    })
+  }
+
+  fn accept(&mut self, action: Option<String>) {
+    if let Some(selected) = self.list_state.selected() {
+      self.ret = self.filtered.keys().nth(selected).cloned();
+      self.ret_action = action;
+      self.quit = true;
+    }
+  }
 }
 
-fn handle_event_ui(s: &mut State, e: Event) {
+fn handle_event_ui<T: Item>(s: &mut State<T>, e: Event) {
   match e {
-    Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-      KeyCode::Esc => s.quit = true,
-      KeyCode::Backspace => s.backspace(),
-      KeyCode::Left => s.move_cursor(-1),
-      KeyCode::Right => s.move_cursor(1),
-      KeyCode::Up => s.move_selected_item(1),
-      KeyCode::Down => s.move_selected_item(-1),
-      KeyCode::Home => s.move_cursor(-1000),
-      KeyCode::End => s.move_cursor(1000),
-      KeyCode::Enter => {
-        if let Some(selected) = s.list_state.selected() {
-          s.ret = s
-            .filtered
-            .iter()
-            .nth(selected)
-            .map(|(_, idx)| s.list[*idx].abs.clone());
-          s.quit = true;
+    Event::Key(key) if key.kind == KeyEventKind::Press => {
+      if let Some(action) = s.keymap.get(&key).cloned() {
+        handle_action_ui(s, action);
+      } else if let KeyCode::Char(c) = key.code {
+        // Insert only plain characters, and ignore unbound chords
+        let chord = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        if !key.modifiers.intersects(chord) {
+          s.insert(c);
         }
       }
-      KeyCode::Char(to_insert) => {
-        if key.modifiers & KeyModifiers::CONTROL != KeyModifiers::empty() {
-          match to_insert {
-            'n' => s.move_selected_item(-1),
-            'p' => s.move_selected_item(1),
-            'a' => s.move_cursor(-1000),
-            'e' => s.move_cursor(1000),
-            _ => s.quit = true,
-          }
-        } else if key.modifiers & KeyModifiers::ALT != KeyModifiers::empty() {
-          match to_insert {
-            'j' => s.move_selected_item(-1),
-            'k' => s.move_selected_item(1),
-            'h' => s.move_cursor(-1),
-            'l' => s.move_cursor(1),
-            _ => {}
-          }
-        } else {
-          s.insert(to_insert)
-        }
+    }
+    Event::Mouse(mouse) => handle_mouse_ui(s, mouse),
+    _ => {}
+  }
+}
+
+/// Interval of clicks to be a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+fn handle_mouse_ui<T: Item>(s: &mut State<T>, mouse: MouseEvent) {
+  match mouse.kind {
+    MouseEventKind::ScrollUp => handle_action_ui(s, Action::Up),
+    MouseEventKind::ScrollDown => handle_action_ui(s, Action::Down),
+    MouseEventKind::Down(MouseButton::Left) => {
+      let area = s.list_area;
+      if !area.contains(Position::new(mouse.column, mouse.row)) {
+        return;
+      }
+      // Row from the first item, which is at the bottom unless reversed
+      let row = if s.reverse {
+        mouse.row - area.y
+      } else {
+        area.bottom() - 1 - mouse.row
+      };
+      let index = s.list_state.offset() + row as usize;
+      if index >= s.filtered.len() {
+        return;
+      }
+      s.list_state.select(Some(index));
+      s.need_to_redraw = true;
+
+      let now = Instant::now();
+      let double = matches!(
+        s.last_click,
+        Some((i, t)) if i == index && now - t < DOUBLE_CLICK
+      );
+      if double {
+        s.accept(None);
+      } else {
+        s.last_click = Some((index, now));
       }
-      _ => {}
-    },
+    }
     _ => {}
   }
 }
 
-fn draw_ui(f: &mut Frame, s: &mut State) {
-  let vertical = Layout::vertical([
-    Constraint::Min(1),
-    Constraint::Length(1),
-    Constraint::Length(1),
-  ]);
-  let [list_area, bd_area, input_area] = vertical.areas(f.area());
+fn handle_action_ui<T: Item>(s: &mut State<T>, action: Action) {
+  let page = s.page_size as isize;
+  // Items are listed from the bottom, unless the layout is reversed
+  let up = if s.reverse { -1 } else { 1 };
+  match action {
+    Action::Ignore => {}
+    Action::Abort => s.quit = true,
+    Action::Accept => s.accept(None),
+    Action::AcceptWith(name) => s.accept(Some(name)),
+    Action::Up => s.move_selected_item(up),
+    Action::Down => s.move_selected_item(-up),
+    Action::PageUp => s.move_selected_item(up * page),
+    Action::PageDown => s.move_selected_item(-up * page),
+    Action::First => s.move_selected_item(isize::MIN),
+    Action::Last => s.move_selected_item(isize::MAX),
+    Action::BackwardChar => s.move_cursor(-1),
+    Action::ForwardChar => s.move_cursor(1),
+    Action::BackwardWord => s.set_cursor(s.word_start_before(s.cursor)),
+    Action::ForwardWord => s.set_cursor(s.word_end_after(s.cursor)),
+    Action::BeginningOfLine => s.set_cursor(0),
+    Action::EndOfLine => s.set_cursor(s.query.len()),
+    Action::BackwardDeleteChar => s.backspace(),
+    Action::DeleteChar => s.delete_range(s.cursor, s.cursor + 1),
+    Action::DeleteWord => {
+      s.delete_range(s.word_start_before(s.cursor), s.cursor)
+    }
+    Action::KillLine => s.delete_range(s.cursor, s.query.len()),
+    Action::ClearQuery => s.delete_range(0, s.query.len()),
+    Action::ToggleFiles => s.toggle(Toggle::Files),
+    Action::ToggleHidden => s.toggle(Toggle::Hidden),
+    Action::CycleBase => s.toggle(Toggle::Base),
+    Action::CycleMode => s.cycle_mode(),
+    Action::PreviousHistory => s.move_history(s.history_pos.saturating_sub(1)),
+    Action::NextHistory => s.move_history(s.history_pos + 1),
+    Action::SearchHistory => s.search_history(),
+  }
+}
+
+fn draw_ui<T>(f: &mut Frame, s: &mut State<T>) {
+  let (list_area, bd_area, input_area) = if s.reverse {
+    let vertical = Layout::vertical([
+      Constraint::Length(1),
+      Constraint::Length(1),
+      Constraint::Min(1),
+    ]);
+    let [input_area, bd_area, list_area] = vertical.areas(f.area());
+    (list_area, bd_area, input_area)
+  } else {
+    let vertical = Layout::vertical([
+      Constraint::Min(1),
+      Constraint::Length(1),
+      Constraint::Length(1),
+    ]);
+    let [list_area, bd_area, input_area] = vertical.areas(f.area());
+    (list_area, bd_area, input_area)
+  };
 
   {
     // Draw input line
@@ -217,12 +695,12 @@ fn draw_ui(f: &mut Frame, s: &mut State) {
     let [prompt_area, query_area] = input_horizontal.areas(input_area);
 
     // Draw prompt
-    let prompt = Paragraph::new(">").style(Style::default());
+    let prompt = Paragraph::new(">").style(s.theme.prompt);
     f.render_widget(prompt, prompt_area);
 
     // Draw query
     let input = Paragraph::new(s.query_string.as_str())
-      .style(Style::default())
+      .style(s.theme.query)
       .block(Block::default());
     f.render_widget(input, query_area);
 
@@ -236,45 +714,91 @@ fn draw_ui(f: &mut Frame, s: &mut State) {
   {
     // Draw border
     let mut title = format!(" {}/{} ", s.filtered.len(), s.list.len());
-    if s.unfiltered_count > 0 {
-      title.push_str(format!("({} left) ", s.unfiltered_count).as_str());
+    if !s.filtered.is_empty() {
+      let sel = s.list_state.selected().unwrap_or(0);
+      title.push_str(format!("#{} ", sel + 1).as_str());
     }
-    let block = Block::default().borders(Borders::TOP).title(title);
+    if !s.status.is_empty() {
+      title.push_str(format!("[{}] ", s.status).as_str());
+    }
+    if s.ed.error().is_some() {
+      title.push_str(format!("(invalid {}) ", s.mode).as_str());
+    } else if s.mode != Mode::Fuzzy {
+      title.push_str(format!("({}) ", s.mode).as_str());
+    }
+    if s.scorer.is_tolerant() {
+      title.push_str("(typos) ");
+    }
+    if s.scorer.remaining() > 0 {
+      title.push_str(format!("({} left) ", s.scorer.remaining()).as_str());
+    }
+    let borders = if s.reverse {
+      Borders::BOTTOM
+    } else {
+      Borders::TOP
+    };
+    let block = Block::default()
+      .borders(borders)
+      .border_style(s.theme.border)
+      .title(Span::styled(title, s.theme.counter));
     f.render_widget(block, bd_area);
   }
 
   {
     // Draw list items
     let height = list_area.height as usize;
+    s.page_size = height.max(1);
+    s.list_area = list_area;
 
     // Move offset into screen
     let sel = s.list_state.selected().unwrap_or(0);
     s.list_state.select(Some(sel));
 
     let off = s.list_state.offset_mut();
-    *off = (*off).clamp(sel.saturating_sub(height - 1), sel);
+    *off = (*off).clamp(sel.saturating_sub(height.saturating_sub(1)), sel);
 
-    // Render
-    let iter = s
-      .filtered
-      .iter()
-      .map(|(_key, val)| s.list_items[*val].as_str());
-    let path_list = List::new(iter)
-      .direction(ListDirection::BottomToTop)
+    // Render only items in the screen
+    let offset = s.list_state.offset();
+    let (ed, theme) = (&mut s.ed, &s.theme);
+    let items = s.filtered.values().skip(offset).take(height).map(|val| {
+      let item = &s.scorer.texts()[*val];
+      let matched = ed.match_positions(&item.text, item.prefix_len);
+      item_line(theme, &matched, &item.text, item.prefix_len)
+    });
+    let mut screen_state =
+      ListState::default().with_selected(Some(sel - offset));
+    let path_list = List::new(items)
+      .direction(if s.reverse {
+        ListDirection::TopToBottom
+      } else {
+        ListDirection::BottomToTop
+      })
       .highlight_spacing(HighlightSpacing::Always)
       .highlight_symbol("* ")
-      .highlight_style(Style::default().fg(style::Color::Red))
+      .highlight_style(s.theme.selected)
       .block(Block::default());
-    f.render_stateful_widget(path_list, list_area, &mut s.list_state);
+    f.render_stateful_widget(path_list, list_area, &mut screen_state);
+
+    // Draw scrollbar, where the best match is at the bottom unless reversed
+    if s.filtered.len() > height {
+      let last = s.filtered.len() - height;
+      let position = if s.reverse { offset } else { last - offset };
+      let mut scrollbar_state =
+        ScrollbarState::new(last + 1).position(position);
+      let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .style(s.theme.border);
+      f.render_stateful_widget(scrollbar, list_area, &mut scrollbar_state);
+    }
   }
 }
 
-fn run_ui(s: &mut State) -> io::Result<String> {
-  // Clean-up UI
-  enable_raw_mode()?;
-  stderr().execute(EnterAlternateScreen)?;
-  let mut terminal = Terminal::new(CrosstermBackend::new(stderr()))?;
-
+fn event_loop<T: Item, B: Backend, E: EventSource>(
+  s: &mut State<T>,
+  terminal: &mut Terminal<B>,
+  events: &mut E,
+) -> io::Result<()> {
   while !s.quit {
     let start_time = std::time::Instant::now();
     if s.need_to_redraw {
@@ -283,20 +807,939 @@ fn run_ui(s: &mut State) -> io::Result<String> {
     }
 
     while start_time.elapsed() < Duration::from_millis(35)
-      && event::poll(start_time.elapsed())?
+      && events.poll(start_time.elapsed())?
     {
-      handle_event_ui(s, event::read()?);
+      handle_event_ui(s, events.read()?);
     }
 
     s.filter_slightly(Duration::from_millis(15));
   }
+  Ok(())
+}
+
+/// Time to wait for the terminal to report the cursor position
+const CURSOR_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Cursor position, read from the terminal device directly,
+/// with bytes typed before the report.
+/// The position is None if the terminal does not report it in time.
+/// crossterm writes the query to stdout, which may be captured by shells.
+#[cfg(unix)]
+fn cursor_position() -> io::Result<(Option<Position>, Vec<u8>)> {
+  use std::io::Read;
+  use std::os::fd::AsRawFd;
+
+  let mut tty = std::fs::OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open("/dev/tty")?;
+  tty.write_all(b"\x1b[6n")?;
+  tty.flush()?;
+
+  let deadline = Instant::now() + CURSOR_TIMEOUT;
+  let mut input = vec![];
+  loop {
+    if let Some((range, position)) = find_cursor_report(&input) {
+      input.drain(range);
+      return Ok((Some(position), input));
+    }
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    let mut fds = libc::pollfd {
+      fd: tty.as_raw_fd(),
+      events: libc::POLLIN,
+      revents: 0,
+    };
+    let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+    // SAFETY: fds is a valid pollfd for the open tty
+    match unsafe { libc::poll(&mut fds, 1, timeout_ms) } {
+      0 => return Ok((None, input)),
+      n if n < 0 => {
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+          return Err(err);
+        }
+      }
+      _ => {
+        let mut buf = [0u8; 64];
+        let n = tty.read(&mut buf)?;
+        input.extend_from_slice(&buf[..n]);
+      }
+    }
+  }
+}
+
+#[cfg(not(unix))]
+fn cursor_position() -> io::Result<(Option<Position>, Vec<u8>)> {
+  let position = crossterm::cursor::position().ok();
+  Ok((position.map(|(x, y)| Position::new(x, y)), vec![]))
+}
+
+/// Find a cursor position report, `ESC [ row ; col R`, in the input
+#[cfg_attr(not(unix), allow(dead_code))]
+fn find_cursor_report(
+  input: &[u8],
+) -> Option<(std::ops::Range<usize>, Position)> {
+  let number = |bytes: &[u8]| -> Option<(u16, usize)> {
+    let len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    let n = std::str::from_utf8(&bytes[..len]).ok()?.parse().ok()?;
+    Some((n, len))
+  };
+  (0..input.len()).find_map(|start| {
+    let rest = input[start..].strip_prefix(b"\x1b[")?;
+    let (row, row_len) = number(rest)?;
+    let rest = rest[row_len..].strip_prefix(b";")?;
+    let (col, col_len) = number(rest)?;
+    rest[col_len..].strip_prefix(b"R")?;
+    let end = start + 2 + row_len + 1 + col_len + 1;
+    let position = Position::new(col.saturating_sub(1), row.saturating_sub(1));
+    Some((start..end, position))
+  })
+}
+
+/// Characters typed in the input, without control characters and
+/// escape sequences such as arrow keys
+fn typed_chars(input: &[u8]) -> Vec<char> {
+  let text = String::from_utf8_lossy(input);
+  let mut chars = vec![];
+  let mut iter = text.chars().peekable();
+  while let Some(c) = iter.next() {
+    if c == '\x1b' {
+      // Skip the introducer and parameters, up to the final character
+      if iter.next_if(|c| *c == '[' || *c == 'O').is_some() {
+        while iter.next_if(|c| !('@'..='~').contains(c)).is_some() {}
+      }
+      iter.next();
+    } else if !c.is_control() {
+      chars.push(c);
+    }
+  }
+  chars
+}
+
+fn run_ui<T: Item, W: Write>(
+  s: &mut State<T>,
+  mut out: W,
+  height: Option<Height>,
+  mouse: bool,
+) -> io::Result<()> {
+  // Clean-up UI
+  enable_raw_mode()?;
+  if mouse {
+    out.execute(EnableMouseCapture)?;
+  }
 
-  stderr().execute(LeaveAlternateScreen)?;
+  let result = match height {
+    None => run_full_screen_ui(s, &mut out),
+    Some(height) => run_inline_ui(s, &mut out, height),
+  };
+
+  if mouse {
+    out.execute(DisableMouseCapture)?;
+  }
   disable_raw_mode()?;
-  Ok(s.ret.clone().unwrap_or("".to_string()))
+  result
+}
+
+fn run_full_screen_ui<T: Item, W: Write>(
+  s: &mut State<T>,
+  mut out: W,
+) -> io::Result<()> {
+  out.execute(EnterAlternateScreen)?;
+  let mut terminal = Terminal::new(CrosstermBackend::new(&mut out))?;
+  let result = event_loop(s, &mut terminal, &mut TerminalEvents);
+  drop(terminal);
+  out.execute(LeaveAlternateScreen)?;
+  result
+}
+
+fn run_inline_ui<T: Item, W: Write>(
+  s: &mut State<T>,
+  mut out: W,
+  height: Height,
+) -> io::Result<()> {
+  let (cursor, typed) = cursor_position()?;
+  // Keys typed while waiting for the report are kept in the query
+  for c in typed_chars(&typed) {
+    s.insert(c);
+  }
+  // Terminals not reporting the cursor get the full screen
+  let Some(cursor) = cursor else {
+    return run_full_screen_ui(s, out);
+  };
+  let (width, screen_height) = crossterm::terminal::size()?;
+  let lines = height.lines(screen_height);
+
+  // Make room below the cursor, scrolling if needed
+  for _ in 1..lines {
+    out.write_all(b"\n")?;
+  }
+  out.flush()?;
+  let top = cursor.y.min(screen_height.saturating_sub(lines));
+  let area = Rect::new(0, top, width, lines);
+
+  let backend = CrosstermBackend::new(&mut out);
+  let options = TerminalOptions {
+    viewport: Viewport::Fixed(area),
+  };
+  let mut terminal = Terminal::with_options(backend, options)?;
+  let result = event_loop(s, &mut terminal, &mut TerminalEvents);
+  drop(terminal);
+
+  // Erase the finder, and put the cursor back
+  out.queue(MoveTo(0, top))?;
+  out.queue(Clear(ClearType::FromCursorDown))?;
+  out.queue(MoveTo(cursor.x, top))?;
+  out.execute(Show)?;
+  result
+}
+
+/// Height of the inline finder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Height {
+  Lines(u16),
+  Percent(u16),
+}
+
+impl Height {
+  /// Number of lines in the screen, at least 3 lines
+  pub fn lines(&self, screen_height: u16) -> u16 {
+    let lines = match *self {
+      Height::Lines(n) => n,
+      Height::Percent(p) => (screen_height as u32 * p as u32 / 100) as u16,
+    };
+    lines.clamp(3, screen_height.max(3))
+  }
+}
+
+impl FromStr for Height {
+  type Err = String;
+
+  /// Parse lines (`20`) or a percentage of the screen (`40%`)
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || format!("Invalid height '{}'", s);
+    match s.strip_suffix('%') {
+      Some(p) => p
+        .parse()
+        .ok()
+        .filter(|p| *p <= 100)
+        .map(Height::Percent)
+        .ok_or_else(invalid),
+      None => s.parse().map(Height::Lines).map_err(|_| invalid()),
+    }
+  }
+}
+
+/// Fuzzy finder over items loaded from a source
+pub struct Finder<T> {
+  source: Box<dyn Source<T>>,
+  query: String,
+  keymap: Keymap,
+  height: Option<Height>,
+  reverse: bool,
+  mouse: bool,
+  history: Option<History>,
+  theme: Theme,
+  matcher: fuzzy::EditDist,
+  mode: Mode,
+}
+
+impl<T: Item + 'static> Finder<T> {
+  pub fn new(source: impl Source<T> + 'static) -> Self {
+    Self {
+      source: Box::new(source),
+      query: String::new(),
+      keymap: Keymap::default(),
+      height: None,
+      reverse: false,
+      mouse: true,
+      history: None,
+      theme: Theme::default(),
+      matcher: fuzzy::EditDist::new(),
+      mode: Mode::default(),
+    }
+  }
+
+  /// Set the initial query
+  pub fn query(mut self, query: &str) -> Self {
+    self.query = query.to_string();
+    self
+  }
+
+  /// Set the key bindings
+  pub fn keymap(mut self, keymap: Keymap) -> Self {
+    self.keymap = keymap;
+    self
+  }
+
+  /// Render inline below the cursor with the height,
+  /// rather than in the alternate screen
+  pub fn height(mut self, height: Option<Height>) -> Self {
+    self.height = height;
+    self
+  }
+
+  /// Put the query at the top, and list items from the top
+  pub fn reverse(mut self, reverse: bool) -> Self {
+    self.reverse = reverse;
+    self
+  }
+
+  /// Capture the mouse, to select items by clicks and scroll the list.
+  /// Enabled by default.
+  pub fn mouse(mut self, mouse: bool) -> Self {
+    self.mouse = mouse;
+    self
+  }
+
+  /// Set the matcher with options such as smart-case,
+  /// whose query is replaced by the query of the finder
+  pub fn matcher(mut self, matcher: fuzzy::EditDist) -> Self {
+    self.matcher = matcher;
+    self
+  }
+
+  /// Set the initial matching mode, which can be cycled in the finder
+  pub fn mode(mut self, mode: Mode) -> Self {
+    self.mode = mode;
+    self
+  }
+
+  /// Set the color theme
+  pub fn theme(mut self, theme: Theme) -> Self {
+    self.theme = theme;
+    self
+  }
+
+  /// Query history, where the accepted query is added and saved
+  pub fn history(mut self, history: History) -> Self {
+    self.history = Some(history);
+    self
+  }
+
+  /// Bind a key to accept the selected item with the named action
+  pub fn action(
+    mut self,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+    name: &str,
+  ) -> Self {
+    let action = Action::AcceptWith(name.to_string());
+    self.keymap.bind(code, modifiers, action);
+    self
+  }
+
+  /// Run the finder on stderr.
+  /// Return None if aborted, or an error if the terminal fails.
+  pub fn run(self) -> io::Result<Option<Selection<T>>> {
+    self.run_on(stderr())
+  }
+
+  /// Run the finder on the given terminal output
+  pub fn run_on<W: Write>(self, out: W) -> io::Result<Option<Selection<T>>> {
+    let (height, mouse) = (self.height, self.mouse);
+    let mut s = self.into_state();
+    run_ui(&mut s, out, height, mouse)?;
+    Ok(s.into_selection())
+  }
+
+  /// Run the finder on the given backend and events,
+  /// without setting up the terminal (e.g. for tests)
+  pub fn run_with<B: Backend, E: EventSource>(
+    self,
+    terminal: &mut Terminal<B>,
+    events: &mut E,
+  ) -> io::Result<Option<Selection<T>>> {
+    let mut s = self.into_state();
+    event_loop(&mut s, terminal, events)?;
+    Ok(s.into_selection())
+  }
+
+  fn into_state(mut self) -> State<T> {
+    let mut s =
+      State::new(self.source.load(), &self.query, self.matcher, self.mode);
+    s.status = self.source.status();
+    s.source = Some(self.source);
+    s.keymap = self.keymap;
+    s.reverse = self.reverse;
+    s.theme = self.theme;
+    s.history_pos = self.history.as_ref().map_or(0, |h| h.entries().len());
+    s.history = self.history;
+    s
+  }
 }
 
-pub fn run(list: Vec<path::PathItem>, init_query: &String) -> Option<String> {
-  let mut s = State::new(list, init_query);
-  run_ui(&mut s).ok().filter(|s| !s.is_empty())
+#[cfg(test)]
+mod tests {
+  use std::collections::VecDeque;
+
+  use crossterm::event::KeyEvent;
+  use ratatui::backend::TestBackend;
+
+  use super::*;
+
+  /// Scripted events. Each event is delivered on every other poll,
+  /// so the finder can filter items between events.
+  /// After the script ends, Esc is delivered.
+  struct Script {
+    events: VecDeque<Event>,
+    ready: bool,
+  }
+
+  impl EventSource for Script {
+    fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+      self.ready = !self.ready;
+      Ok(self.ready)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+      Ok(self.events.pop_front().unwrap_or(key(KeyCode::Esc)))
+    }
+  }
+
+  fn key(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::from(code))
+  }
+
+  fn ctrl(c: char) -> Event {
+    Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+  }
+
+  fn run_script<T: Item + 'static>(
+    finder: Finder<T>,
+    events: Vec<Event>,
+  ) -> (Option<Selection<T>>, Terminal<TestBackend>) {
+    let mut terminal = Terminal::new(TestBackend::new(30, 8)).unwrap();
+    let mut script = Script {
+      events: events.into(),
+      ready: true,
+    };
+    let sel = finder.run_with(&mut terminal, &mut script).unwrap();
+    (sel, terminal)
+  }
+
+  fn finder(items: &[&str], query: &str) -> Finder<String> {
+    let items: Vec<String> = items.iter().map(|s| s.to_string()).collect();
+    Finder::new(items).query(query)
+  }
+
+  fn selected(items: &[&str], query: &str, events: Vec<Event>) -> String {
+    let (sel, _) = run_script(finder(items, query), events);
+    sel.map(|s| s.item).unwrap_or_default()
+  }
+
+  fn rendered_lines(terminal: &Terminal<TestBackend>) -> Vec<String> {
+    let buf = terminal.backend().buffer();
+    (0..buf.area.height)
+      .map(|y| {
+        (0..buf.area.width)
+          .map(|x| buf[(x, y)].symbol())
+          .collect::<String>()
+          .trim_end()
+          .to_string()
+      })
+      .collect()
+  }
+
+  fn row_of(lines: &[String], text: &str) -> usize {
+    lines.iter().position(|l| l.ends_with(text)).unwrap()
+  }
+
+  // With an empty query, the cost is the length, so "a" is the best.
+  const ITEMS: [&str; 3] = ["ccc", "a", "bb"];
+
+  #[test]
+  fn enter_selects_best_match() {
+    let sel = selected(
+      &["alpha", "beta", "gamma"],
+      "bet",
+      vec![key(KeyCode::Enter)],
+    );
+    assert_eq!(sel, "beta");
+  }
+
+  #[test]
+  fn esc_quits_without_selection() {
+    let (sel, _) = run_script(finder(&ITEMS, ""), vec![key(KeyCode::Esc)]);
+    assert!(sel.is_none());
+  }
+
+  #[test]
+  fn best_match_is_at_the_bottom() {
+    let (_, terminal) = run_script(finder(&ITEMS, ""), vec![]);
+    let lines = rendered_lines(&terminal);
+    assert!(lines[row_of(&lines, "a")].starts_with("* "));
+    assert!(row_of(&lines, "a") > row_of(&lines, "bb"));
+    assert!(row_of(&lines, "bb") > row_of(&lines, "ccc"));
+    assert!(lines[6].contains(" 3/3 "));
+    assert_eq!(lines[7], ">");
+  }
+
+  #[test]
+  fn invalid_patterns_are_shown_in_the_border() {
+    let regex = finder(&ITEMS, "(a").mode(Mode::Regex);
+    let (_, terminal) = run_script(regex, vec![]);
+    assert!(rendered_lines(&terminal)[6].contains(" 0/3 (invalid regex) "));
+
+    let regex = finder(&ITEMS, "a").mode(Mode::Regex);
+    let (_, terminal) = run_script(regex, vec![]);
+    assert!(rendered_lines(&terminal)[6].contains(" 1/3 #1 (regex) "));
+  }
+
+  #[test]
+  fn up_and_ctrl_p_move_selection_up() {
+    let up = vec![key(KeyCode::Up)];
+    let (_, terminal) = run_script(finder(&ITEMS, ""), up);
+    let lines = rendered_lines(&terminal);
+    assert!(lines[row_of(&lines, "bb")].starts_with("* "));
+
+    let enter = key(KeyCode::Enter);
+    assert_eq!(
+      selected(&ITEMS, "", vec![key(KeyCode::Up), enter.clone()]),
+      "bb"
+    );
+    assert_eq!(selected(&ITEMS, "", vec![ctrl('p'), enter.clone()]), "bb");
+    assert_eq!(
+      selected(&ITEMS, "", vec![ctrl('p'), ctrl('p'), ctrl('n'), enter]),
+      "bb"
+    );
+  }
+
+  #[test]
+  fn down_and_ctrl_n_move_selection_down() {
+    let enter = key(KeyCode::Enter);
+    let up2 = || vec![key(KeyCode::Up), key(KeyCode::Up)];
+    let mut events = up2();
+    events.extend([key(KeyCode::Down), enter.clone()]);
+    assert_eq!(selected(&ITEMS, "", events), "bb");
+    let mut events = up2();
+    events.extend([ctrl('n'), enter]);
+    assert_eq!(selected(&ITEMS, "", events), "bb");
+  }
+
+  #[test]
+  fn selection_is_clamped_to_filtered_items() {
+    let items = ["ab", "axb", "x", "y", "z", "w"];
+    let mut events = vec![key(KeyCode::Up); 10];
+    events.push(key(KeyCode::Enter));
+    assert_eq!(selected(&items, "ab", events), "axb");
+  }
+
+  #[test]
+  fn moving_in_empty_list_does_not_panic() {
+    let events = vec![key(KeyCode::Up), key(KeyCode::Down)];
+    let (sel, _) = run_script(finder(&[], "q"), events);
+    assert!(sel.is_none());
+  }
+
+  #[test]
+  fn typing_filters_and_resets_selection() {
+    let items = ["apple", "banana", "cherry"];
+    let events = vec![
+      key(KeyCode::Up),
+      key(KeyCode::Char('n')),
+      key(KeyCode::Char('n')),
+      key(KeyCode::Enter),
+    ];
+    assert_eq!(selected(&items, "", events), "banana");
+
+    let (_, terminal) = run_script(
+      finder(&items, ""),
+      vec![key(KeyCode::Char('c')), key(KeyCode::Char('h'))],
+    );
+    let lines = rendered_lines(&terminal);
+    assert!(lines[6].contains(" 1/3 "));
+    assert_eq!(lines[7], "> ch");
+  }
+
+  #[test]
+  fn editing_non_ascii_initial_query() {
+    let events = vec![
+      key(KeyCode::Char('a')),
+      key(KeyCode::Left),
+      key(KeyCode::Backspace),
+      key(KeyCode::Enter),
+    ];
+    assert_eq!(selected(&["한a", "a", "b"], "한글", events), "한a");
+  }
+
+  #[test]
+  fn unbound_ctrl_chord_does_not_quit() {
+    let events = vec![ctrl('z'), ctrl('t'), key(KeyCode::Enter)];
+    assert_eq!(selected(&ITEMS, "", events), "a");
+  }
+
+  #[test]
+  fn editing_keys() {
+    let items = ["foo/bar", "foo/baz", "qux/q"];
+    let enter = key(KeyCode::Enter);
+    let alt =
+      |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT));
+
+    // Ctrl-W deletes the word "zzz"
+    let events = vec![ctrl('w'), enter.clone()];
+    assert_eq!(selected(&items, "qux/zzz", events), "qux/q");
+    // Ctrl-U clears the query
+    let events = vec![ctrl('u'), key(KeyCode::Char('q')), enter.clone()];
+    assert_eq!(selected(&items, "zzz", events), "qux/q");
+    // Alt-B moves to the word "zzz", and Ctrl-K kills it
+    let events = vec![alt('b'), ctrl('k'), enter.clone()];
+    assert_eq!(selected(&items, "baz/zzz", events), "");
+    let events = vec![alt('b'), ctrl('k'), enter.clone()];
+    assert_eq!(selected(&items, "qux/zzz", events), "qux/q");
+    // Ctrl-A and Alt-F move to the end of "qux", Ctrl-D deletes " zzz"
+    let events = vec![
+      ctrl('a'),
+      alt('f'),
+      ctrl('d'),
+      ctrl('d'),
+      ctrl('d'),
+      ctrl('d'),
+      enter,
+    ];
+    assert_eq!(selected(&items, "qux zzz", events), "qux/q");
+  }
+
+  #[test]
+  fn page_keys_move_by_list_height() {
+    let items: Vec<String> = (1..=20).map(|n| "x".repeat(n)).collect();
+    let items: Vec<&str> = items.iter().map(|s| s.as_str()).collect();
+    let events = vec![key(KeyCode::PageUp), key(KeyCode::Enter)];
+    // 6 rows for the list, in the terminal of height 8
+    assert_eq!(selected(&items, "", events), "x".repeat(7));
+    let events = vec![
+      key(KeyCode::PageUp),
+      key(KeyCode::PageUp),
+      key(KeyCode::PageDown),
+      key(KeyCode::Enter),
+    ];
+    assert_eq!(selected(&items, "", events), "x".repeat(7));
+  }
+
+  #[test]
+  fn keymap_overrides_keys() {
+    let mut keymap = Keymap::default();
+    keymap.bind_spec("ctrl-j:down,ctrl-k:up").unwrap();
+    let finder = finder(&ITEMS, "").keymap(keymap);
+    let events = vec![ctrl('k'), ctrl('k'), ctrl('j'), key(KeyCode::Enter)];
+    let (sel, _) = run_script(finder, events);
+    assert_eq!(sel.unwrap().item, "bb");
+  }
+
+  #[test]
+  fn action_key_accepts_with_action() {
+    let finder = finder(&ITEMS, "bb").action(
+      KeyCode::Char('o'),
+      KeyModifiers::CONTROL,
+      "open",
+    );
+    let (sel, _) = run_script(finder, vec![ctrl('o')]);
+    let sel = sel.unwrap();
+    assert_eq!(sel.item, "bb");
+    assert_eq!(sel.action.as_deref(), Some("open"));
+  }
+
+  #[test]
+  fn reverse_layout_lists_from_the_top() {
+    let reversed = || finder(&ITEMS, "").reverse(true);
+    let (_, terminal) = run_script(reversed(), vec![key(KeyCode::Up)]);
+    let lines = rendered_lines(&terminal);
+    assert_eq!(lines[0], ">");
+    assert!(lines[1].contains(" 3/3 "));
+    assert!(row_of(&lines, "a") < row_of(&lines, "bb"));
+    // Up moves to the better match, which is above
+    assert!(lines[row_of(&lines, "a")].starts_with("* "));
+
+    let (sel, _) =
+      run_script(reversed(), vec![key(KeyCode::Down), key(KeyCode::Enter)]);
+    assert_eq!(sel.unwrap().item, "bb");
+  }
+
+  #[test]
+  fn parse_height() {
+    assert_eq!("20".parse(), Ok(Height::Lines(20)));
+    assert_eq!("40%".parse(), Ok(Height::Percent(40)));
+    assert!("120%".parse::<Height>().is_err());
+    assert!("tall".parse::<Height>().is_err());
+    assert_eq!(Height::Percent(40).lines(50), 20);
+    assert_eq!(Height::Lines(1).lines(50), 3);
+    assert_eq!(Height::Lines(80).lines(50), 50);
+  }
+
+  /// Source which includes "hidden" items only if toggled
+  struct Toggled {
+    hidden: bool,
+  }
+
+  impl Source<String> for Toggled {
+    fn load(&mut self) -> Vec<String> {
+      let mut items = vec!["a".to_string()];
+      if self.hidden {
+        items.push(".b".to_string());
+      }
+      items
+    }
+
+    fn toggle(&mut self, toggle: Toggle) -> bool {
+      self.hidden ^= toggle == Toggle::Hidden;
+      toggle == Toggle::Hidden
+    }
+
+    fn status(&self) -> String {
+      if self.hidden { "hidden" } else { "" }.to_string()
+    }
+  }
+
+  #[test]
+  fn toggle_reloads_source() {
+    let toggled = || Finder::new(Toggled { hidden: false }).query("b");
+    let alt_dot =
+      Event::Key(KeyEvent::new(KeyCode::Char('.'), KeyModifiers::ALT));
+    let (_, terminal) = run_script(toggled(), vec![alt_dot.clone()]);
+    let lines = rendered_lines(&terminal);
+    assert!(lines[6].contains(" 1/2 #1 [hidden] "));
+    assert!(lines[row_of(&lines, ".b")].starts_with("* "));
+
+    // Toggling twice hides them again, and other toggles are ignored
+    let events = vec![alt_dot.clone(), ctrl('t'), alt_dot, key(KeyCode::Tab)];
+    let (_, terminal) = run_script(toggled(), events);
+    let lines = rendered_lines(&terminal);
+    assert!(lines[6].contains(" 0/1 "));
+    assert!(!lines[6].contains("["));
+  }
+
+  fn click(column: u16, row: u16) -> Event {
+    Event::Mouse(MouseEvent {
+      kind: MouseEventKind::Down(MouseButton::Left),
+      column,
+      row,
+      modifiers: KeyModifiers::empty(),
+    })
+  }
+
+  fn scroll(kind: MouseEventKind) -> Event {
+    Event::Mouse(MouseEvent {
+      kind,
+      column: 0,
+      row: 0,
+      modifiers: KeyModifiers::empty(),
+    })
+  }
+
+  #[test]
+  fn click_selects_and_double_click_accepts() {
+    // Items are listed from the bottom of the list (rows 0 to 5)
+    let (_, terminal) = run_script(finder(&ITEMS, ""), vec![click(4, 3)]);
+    let lines = rendered_lines(&terminal);
+    assert_eq!(row_of(&lines, "ccc"), 3);
+    assert!(lines[3].starts_with("* "));
+
+    assert_eq!(selected(&ITEMS, "", vec![click(4, 4), click(4, 4)]), "bb");
+    // Clicks out of items are ignored
+    let events = vec![click(4, 0), click(4, 0), key(KeyCode::Enter)];
+    assert_eq!(selected(&ITEMS, "", events), "a");
+
+    let reversed = finder(&ITEMS, "").reverse(true);
+    let (sel, _) = run_script(reversed, vec![click(4, 4), click(4, 4)]);
+    assert_eq!(sel.unwrap().item, "ccc");
+  }
+
+  #[test]
+  fn scroll_moves_selection() {
+    let up = scroll(MouseEventKind::ScrollUp);
+    let down = scroll(MouseEventKind::ScrollDown);
+    let enter = key(KeyCode::Enter);
+    let events = vec![up.clone(), up.clone(), enter.clone()];
+    assert_eq!(selected(&ITEMS, "", events), "ccc");
+    let events = vec![up.clone(), up, down, enter];
+    assert_eq!(selected(&ITEMS, "", events), "bb");
+  }
+
+  fn alt(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::ALT))
+  }
+
+  #[test]
+  fn history_recalls_queries() {
+    let history = || History::new(vec!["cc".into(), "a".into(), "b".into()]);
+    let with_history = || finder(&ITEMS, "").history(history());
+    let enter = key(KeyCode::Enter);
+
+    let events = vec![alt(KeyCode::Up), alt(KeyCode::Up), enter.clone()];
+    let (sel, _) = run_script(with_history(), events);
+    assert_eq!(sel.unwrap().item, "a");
+
+    // Back to the draft after the latest entry
+    let events = vec![
+      key(KeyCode::Char('c')),
+      alt(KeyCode::Up),
+      alt(KeyCode::Down),
+      alt(KeyCode::Down),
+      enter.clone(),
+    ];
+    let (sel, _) = run_script(with_history(), events);
+    assert_eq!(sel.unwrap().item, "ccc");
+
+    // Search entries containing the typed query
+    let events = vec![key(KeyCode::Char('c')), ctrl('r'), ctrl('r'), enter];
+    let (sel, terminal) = run_script(with_history(), events);
+    assert_eq!(sel.unwrap().item, "ccc");
+    assert_eq!(rendered_lines(&terminal)[7], "> cc");
+  }
+
+  #[test]
+  fn base_prefix_and_matches_are_styled() {
+    let path = |displayed: &str| {
+      let base = Some("src".to_string());
+      PathItem::new(displayed.to_string(), displayed.to_string(), base)
+    };
+    let items = vec![path("src: /main"), path("src: /more/main")];
+    let theme = Theme::dark();
+    let finder = Finder::new(items).query("mn").theme(theme.clone());
+    let (_, terminal) = run_script(finder, vec![]);
+    let lines = rendered_lines(&terminal);
+
+    // Not selected, so only styled by the theme
+    let row = row_of(&lines, "/more/main") as u16;
+    let buf = terminal.backend().buffer();
+    let fg = |x: u16| buf[(x, row)].fg;
+    assert_eq!(lines[row as usize], "  src: /more/main");
+    assert_eq!(fg(2), theme.base.fg.unwrap());
+    assert_eq!(fg(6), theme.base.fg.unwrap());
+    assert_eq!(fg(8), theme.matched.fg.unwrap());
+    assert_eq!(fg(9), ratatui::style::Color::Reset);
+    assert_eq!(fg(16), theme.matched.fg.unwrap());
+  }
+
+  #[test]
+  fn first_and_last_with_scrollbar() {
+    let items: Vec<String> = (0..20).map(|i| format!("item{:02}", i)).collect();
+    let items: Vec<&str> = items.iter().map(String::as_str).collect();
+    let last_col = |lines: &[String], row: usize| lines[row].chars().last();
+
+    // The best match is at the bottom, and so is the thumb
+    let (_, terminal) = run_script(finder(&items, ""), vec![]);
+    let lines = rendered_lines(&terminal);
+    assert!(lines[6].contains(" 20/20 #1 "));
+    assert_eq!(last_col(&lines, 5), Some('█'));
+    assert_eq!(last_col(&lines, 0), Some('║'));
+
+    let end = alt(KeyCode::Char('>'));
+    let (_, terminal) = run_script(finder(&items, ""), vec![end.clone()]);
+    let lines = rendered_lines(&terminal);
+    assert!(lines[6].contains(" 20/20 #20 "));
+    assert_eq!(last_col(&lines, 0), Some('█'));
+    assert_eq!(last_col(&lines, 5), Some('║'));
+
+    let home = Event::Key(KeyEvent::new(KeyCode::Home, KeyModifiers::CONTROL));
+    let (sel, _) =
+      run_script(finder(&items, "item"), vec![end, home, key(KeyCode::Enter)]);
+    assert_eq!(sel.unwrap().item, "item00");
+  }
+
+  #[test]
+  fn appending_narrows_previous_matches() {
+    let items: Vec<String> = ["ab", "b", "ba", "c"].map(String::from).to_vec();
+    let mut s = State::new(items, "b", fuzzy::EditDist::new(), Mode::Fuzzy);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.len(), 3);
+
+    s.insert('a');
+    assert_eq!(s.scorer.remaining(), 3);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.len(), 1);
+
+    // Editing in the middle rescans all items
+    s.set_cursor(0);
+    s.insert('a');
+    assert_eq!(s.scorer.remaining(), 4);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.len(), 0);
+
+    s.delete_range(0, 1);
+    assert_eq!(s.scorer.remaining(), 4);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.len(), 1);
+  }
+
+  #[test]
+  fn appending_marks_rescans_all_items() {
+    let items: Vec<String> =
+      ["caf\u{e9}", "cafeteria"].map(String::from).to_vec();
+    let mut s = State::new(items, "cafe", fuzzy::EditDist::new(), Mode::Fuzzy);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.values().collect::<Vec<_>>(), vec![&1]);
+
+    // 'e' and U+0301 are composed into 'é', which 'e' did not match
+    s.insert('\u{301}');
+    assert_eq!(s.scorer.remaining(), 2);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.values().collect::<Vec<_>>(), vec![&0]);
+  }
+
+  #[test]
+  fn falls_back_to_typos_without_matches() {
+    let items: Vec<String> =
+      ["repos", "src", "reops"].map(String::from).to_vec();
+    let mut ed = fuzzy::EditDist::new();
+    ed.typos(1);
+    let mut s = State::new(items, "reops", ed, Mode::Fuzzy);
+    s.filter_slightly(Duration::from_secs(1));
+    assert!(!s.scorer.is_tolerant());
+    assert_eq!(s.filtered.values().collect::<Vec<_>>(), vec![&2]);
+
+    s.set_query("rpeos");
+    s.filter_slightly(Duration::from_secs(1));
+    assert!(s.scorer.is_tolerant());
+    s.filter_slightly(Duration::from_secs(1));
+    // "reops" needs two typos
+    assert_eq!(s.filtered.values().collect::<Vec<_>>(), vec![&0]);
+  }
+
+  #[test]
+  fn finds_cursor_reports_among_typed_keys() {
+    let input = b"ma\x1b[12;5Rs\x1b[A\r";
+    let (range, position) = find_cursor_report(input).unwrap();
+    assert_eq!(range, 2..9);
+    assert_eq!(position, Position::new(4, 11));
+    assert_eq!(find_cursor_report(b"\x1b[12;5"), None);
+    assert_eq!(find_cursor_report(b"\x1b[A12;5R"), None);
+
+    let mut rest = input.to_vec();
+    rest.drain(range);
+    assert_eq!(typed_chars(&rest), ['m', 'a', 's']);
+  }
+
+  #[test]
+  fn cycling_mode_rescores_items() {
+    let items: Vec<String> = ["work/foo-api", "work/bar", "api"]
+      .map(String::from)
+      .to_vec();
+    let mut s = State::new(items, "wa", fuzzy::EditDist::new(), Mode::Fuzzy);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.len(), 2);
+
+    // Texts are kept, as only the matcher changes
+    let texts = s.scorer.texts().clone();
+    s.cycle_mode();
+    assert_eq!(s.mode, Mode::Exact);
+    assert!(Arc::ptr_eq(s.scorer.texts(), &texts));
+    s.filter_slightly(Duration::from_secs(1));
+    assert!(s.filtered.is_empty());
+
+    s.set_query("^work/.*-api$");
+    s.cycle_mode();
+    s.cycle_mode();
+    assert_eq!(s.mode, Mode::Regex);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.values().collect::<Vec<_>>(), vec![&0]);
+
+    // Appending to a regex may add matches
+    s.set_query("api");
+    s.filter_slightly(Duration::from_secs(1));
+    s.insert('|');
+    s.insert('r');
+    assert_eq!(s.scorer.remaining(), 3);
+    s.filter_slightly(Duration::from_secs(1));
+    assert_eq!(s.filtered.len(), 3);
+  }
 }
@@ -0,0 +1,184 @@
+//! Directory walker which gathers path items under base paths.
+
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::path::{self, PathItem};
+use crate::ui_finder::{Source, Toggle};
+
+/// Builder to walk base paths and gather path items
+#[derive(Clone)]
+pub struct Walker {
+  base_paths: Vec<String>,
+  files: bool,
+  hidden: bool,
+  ignore_file_path: Option<String>,
+  // Index of the only base path to walk, or None for all
+  base: Option<usize>,
+}
+
+impl Walker {
+  pub fn new(base_paths: Vec<String>) -> Self {
+    Self {
+      base_paths,
+      files: false,
+      hidden: false,
+      ignore_file_path: None,
+      base: None,
+    }
+  }
+
+  /// Walker for the base paths and ignore file of the config
+  pub fn from_config(config: &Config) -> Self {
+    Self::new(config.find_base_paths.clone())
+      .ignore_file(config.ignore_file_path.clone())
+  }
+
+  /// Include (non-directory) files
+  pub fn files(mut self, files: bool) -> Self {
+    self.files = files;
+    self
+  }
+
+  /// Include hidden files
+  pub fn hidden(mut self, hidden: bool) -> Self {
+    self.hidden = hidden;
+    self
+  }
+
+  /// Additional ignore file, in the gitignore format
+  pub fn ignore_file(mut self, path: Option<String>) -> Self {
+    self.ignore_file_path = path;
+    self
+  }
+
+  /// Traverse all base paths and gather paths
+  pub fn walk(&self) -> Vec<PathItem> {
+    // Convert base paths to names
+    let base_paths = path::convert_base_paths_to_names(&self.base_paths);
+
+    let paths = Mutex::new(vec![]);
+
+    let selected = base_paths
+      .into_iter()
+      .enumerate()
+      .filter(|(i, _)| self.base.is_none_or(|b| b == *i))
+      .map(|(_, base)| base);
+    for base in selected {
+      let mut builder = ignore::WalkBuilder::new(base.abs.clone());
+      builder.standard_filters(true).hidden(!self.hidden);
+      if let Some(p) = &self.ignore_file_path {
+        if let Some(_err) = builder.add_ignore(p) {
+          // eprintln!("Error to load ignore file({})\n{}", p, _err);
+        }
+      }
+      builder.build_parallel().run(|| {
+        Box::new(|result| {
+          if let Ok(entry) = result {
+            let path = entry.path();
+            // Skip paths which cannot be displayed, such as non-UTF-8 ones
+            let Some(abs) = path.to_str().map(str::to_string) else {
+              return ignore::WalkState::Skip;
+            };
+            if path.is_dir() || self.files {
+              let mut paths = paths.lock().unwrap();
+              let displayed = if path.starts_with(&base.abs) {
+                base.displayed.clone() + ": " + &abs[base.abs.len()..]
+              } else {
+                abs.clone()
+              };
+              let base = Some(base.displayed.clone());
+              paths.push(PathItem::new(displayed, abs, base));
+              return ignore::WalkState::Continue;
+            }
+          }
+          ignore::WalkState::Skip
+        })
+      });
+    }
+
+    // Destruct paths from mutex wrapper
+    paths.into_inner().unwrap()
+  }
+}
+
+impl Source<PathItem> for Walker {
+  fn load(&mut self) -> Vec<PathItem> {
+    self.walk()
+  }
+
+  fn toggle(&mut self, toggle: Toggle) -> bool {
+    match toggle {
+      Toggle::Files => self.files = !self.files,
+      Toggle::Hidden => self.hidden = !self.hidden,
+      Toggle::Base => {
+        // Cycle through each base path, and then all of them
+        if self.base_paths.len() < 2 {
+          return false;
+        }
+        self.base = match self.base {
+          None => Some(0),
+          Some(i) if i + 1 < self.base_paths.len() => Some(i + 1),
+          Some(_) => None,
+        };
+      }
+    }
+    true
+  }
+
+  fn status(&self) -> String {
+    let mut status = vec![if self.files { "files" } else { "dirs" }];
+    if self.hidden {
+      status.push("hidden");
+    }
+    let names = path::convert_base_paths_to_names(&self.base_paths);
+    if let Some(base) = self.base.and_then(|i| names.get(i)) {
+      status.push(&base.displayed);
+    }
+    status.join(" ")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn toggle_base_cycles_through_each_base() {
+    let dir = std::env::temp_dir()
+      .join(format!("j2-walker-test-{}", std::process::id()));
+    for sub in ["a/x", "b/y", "b/z", "b/.hidden"] {
+      std::fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    std::fs::write(dir.join("a/file"), "").unwrap();
+    let base = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    let mut walker = Walker::new(vec![base("a"), base("b")]);
+    let walk = |walker: &mut Walker| {
+      let mut found: Vec<String> =
+        walker.load().into_iter().map(|p| p.displayed).collect();
+      found.sort();
+      found
+    };
+
+    assert_eq!(walk(&mut walker), ["a: ", "a: /x", "b: ", "b: /y", "b: /z"]);
+    assert_eq!(walker.status(), "dirs");
+    assert!(walker.toggle(Toggle::Base));
+    assert_eq!(walk(&mut walker), ["a: ", "a: /x"]);
+    assert_eq!(walker.status(), "dirs a");
+    assert!(walker.toggle(Toggle::Base));
+    assert_eq!(walk(&mut walker), ["b: ", "b: /y", "b: /z"]);
+    assert!(walker.toggle(Toggle::Base));
+    assert_eq!(walk(&mut walker).len(), 5);
+    assert_eq!(walker.status(), "dirs");
+
+    assert!(walker.toggle(Toggle::Files));
+    assert!(walker.toggle(Toggle::Hidden));
+    assert!(walk(&mut walker).contains(&"a: /file".to_string()));
+    assert!(walk(&mut walker).contains(&"b: /.hidden".to_string()));
+    assert_eq!(walker.status(), "files hidden");
+
+    let mut single = Walker::new(vec![base("a")]);
+    assert!(!single.toggle(Toggle::Base));
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}